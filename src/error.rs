@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 
 use thiserror::Error;
 
@@ -12,6 +13,19 @@ pub enum CommandError {
 
     #[error("'{command}': Invalid input")]
     InvalidInput { command: String },
+
+    #[error("'{command}': Unexpected output")]
+    UnexpectedOutput { command: String },
+}
+
+/// Errors of TextFSM templates, see [`crate::parsing::textfsm`].
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("Template line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("Input line {line}: {message}")]
+    Rule { line: usize, message: String },
 }
 
 #[derive(Error, Debug)]
@@ -22,6 +36,20 @@ pub enum Error {
     #[error("Authentication failed for user {user}")]
     AuthenticationFailed { user: String },
 
+    #[error("The device requires a password change at login")]
+    PasswordChangeRequired,
+
+    #[error("Connection closed by remote host")]
+    Disconnected,
+
+    /// The prompt was not seen in time; the device may still be producing output.
+    #[error("'{command}': Timed out after {elapsed:?}")]
+    Timeout {
+        command: String,
+        elapsed: Duration,
+        partial_output: String,
+    },
+
     #[error("Failed to execute command {0}")]
     CommandExecution(#[source] CommandError),
 
@@ -31,6 +59,19 @@ pub enum Error {
     #[error("Failed to exit configuration mode")]
     ExitConfigMode,
 
+    #[error("'{operation}' is not supported by this device")]
+    Unsupported { operation: String },
+
+    #[error("No template for '{command}' on {platform}")]
+    NoTemplate { platform: String, command: String },
+
+    #[error("Template {name}: {source}")]
+    Template {
+        name: String,
+        #[source]
+        source: TemplateError,
+    },
+
     #[error("Failed to {operation} to {encoding_name}")]
     EncodingError {
         operation: String,
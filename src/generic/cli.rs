@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use regex::Regex;
+
+use super::connection::Connection;
+use super::dialog::dialog;
+use crate::error::{CommandError, Error};
+
+/// The vendor specific parts of a command line: its prompt, the messages marking
+/// rejected or failed commands and the commands preparing a session.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CliProfile {
+    /// Prompt regex, matched against the end of the output in every mode.
+    pub(crate) prompt: &'static str,
+    /// Messages marking a rejected command.
+    pub(crate) errors: &'static [&'static str],
+    /// Messages marking a failed command like a commit, reported with their line.
+    pub(crate) failures: &'static [&'static str],
+    /// Commands run after the login, e.g. to disable the pager.
+    pub(crate) setup: &'static [&'static str],
+    /// Whether a login landing in user mode, with a `>` prompt, enters privileged mode.
+    pub(crate) user_mode: bool,
+    /// Command entering the configuration mode, run again after a reconnect while
+    /// the mode was entered.
+    pub(crate) config_mode: Option<&'static str>,
+}
+
+impl CliProfile {
+    pub(crate) const DEFAULT: Self = Self {
+        prompt: "",
+        errors: &[],
+        failures: &[],
+        setup: &[],
+        user_mode: false,
+        config_mode: None,
+    };
+
+    /// Fails with `CommandError::InvalidInput` on an error message, or with
+    /// `CommandError::Generic` and the line of a failure message.
+    pub(crate) fn check(&self, command: &str, output: &str) -> Result<(), Error> {
+        if self.errors.iter().any(|&msg| output.contains(msg)) {
+            return Err(Error::CommandExecution(CommandError::InvalidInput {
+                command: command.to_string(),
+            }));
+        }
+
+        let failure = output
+            .lines()
+            .find(|line| self.failures.iter().any(|&msg| line.contains(msg)));
+        if let Some(line) = failure {
+            return Err(Error::CommandExecution(CommandError::Generic {
+                command: command.to_string(),
+                message: line.trim().to_string(),
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+/// The command line of a device behind a connection, running commands with the
+/// prompt and error messages of its profile.
+pub(crate) struct Cli<C: Connection> {
+    pub(crate) connection: C,
+    pub(crate) prompt: Regex,
+    pub(crate) profile: CliProfile,
+    /// Hostname from the login prompt, naming the device in log messages.
+    pub(crate) hostname: Option<String>,
+    pub(crate) enable_password: Option<String>,
+    pub(crate) in_config: bool,
+}
+
+impl<C: Connection> Cli<C> {
+    pub(crate) fn new(connection: C, profile: CliProfile) -> Self {
+        let prompt = Regex::new(profile.prompt).expect("Invalid prompt regex");
+
+        Self::with_prompt(connection, prompt, profile)
+    }
+
+    /// Uses a prompt compiled at runtime instead of the one of the profile.
+    pub(crate) fn with_prompt(connection: C, prompt: Regex, profile: CliProfile) -> Self {
+        Self {
+            connection,
+            prompt,
+            profile,
+            hostname: None,
+            enable_password: None,
+            in_config: false,
+        }
+    }
+
+    pub(crate) fn with_enable_password(mut self, password: Option<&str>) -> Self {
+        self.enable_password = password.map(String::from);
+        self
+    }
+
+    /// Reads the login prompt, entering privileged mode from user mode if the profile
+    /// allows it, and runs the setup commands. Returns the login output.
+    pub(crate) fn initialize(&mut self) -> Result<String, Error> {
+        let output = self.connection.login(&self.prompt)?;
+
+        if self.profile.user_mode && output.trim_end().ends_with('>') {
+            self.enable()?;
+        }
+
+        self.setup()?;
+
+        Ok(output)
+    }
+
+    /// Enters privileged mode, answering the password prompt if an enable password is set.
+    pub(crate) fn enable(&mut self) -> Result<(), Error> {
+        let command = match &self.enable_password {
+            Some(password) => format!("enable\n{}", password),
+            None => "enable".to_string(),
+        };
+
+        self.connection.execute(&command, &self.prompt)?;
+
+        Ok(())
+    }
+
+    /// Runs the setup commands of the profile.
+    pub(crate) fn setup(&mut self) -> Result<(), Error> {
+        for command in self.profile.setup {
+            self.run(command, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes a command once and checks the output for error messages, returning it
+    /// without the echoed command.
+    pub(crate) fn run(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<String, Error> {
+        let output = self.execute(command, timeout)?;
+        self.profile.check(command, &output)?;
+
+        Ok(strip_echo(command, &output))
+    }
+
+    /// Executes a command once, returning the output as read.
+    pub(crate) fn execute(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<String, Error> {
+        match timeout {
+            Some(timeout) => self
+                .connection
+                .execute_with_timeout(command, &self.prompt, timeout),
+            None => self.connection.execute(command, &self.prompt),
+        }
+    }
+
+    /// Executes a command answering its confirmation questions, and checks the output
+    /// for error messages.
+    pub(crate) fn dialog(
+        &mut self,
+        command: &str,
+        answers: &[(&str, &str)],
+    ) -> Result<String, Error> {
+        let output = dialog(&mut self.connection, &self.prompt, command, answers)?;
+        self.profile.check(command, &output)?;
+
+        Ok(output)
+    }
+}
+
+/// A driver running its commands through a `Cli`, which is restored after a lost
+/// connection and the failed command retried once. Drivers override the hooks for
+/// what their profile does not cover.
+pub(crate) trait Driver {
+    type Connection: Connection;
+
+    fn cli(&mut self) -> &mut Cli<Self::Connection>;
+
+    /// Prepares a new session, by default reading the login prompt and running the
+    /// setup commands.
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.cli().initialize().map(drop)
+    }
+
+    /// Gets a new session back to the mode of the lost one, by default the
+    /// configuration mode.
+    fn restore_mode(&mut self) -> Result<(), Error> {
+        let cli = self.cli();
+
+        match cli.profile.config_mode {
+            Some(command) if cli.in_config => cli.run(command, None).map(drop),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the output of a command for what the profile messages do not catch.
+    fn check_output(&mut self, _command: &str, _output: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Executes a command once and checks the output for error messages.
+    fn run_command(&mut self, command: &str, timeout: Option<Duration>) -> Result<String, Error> {
+        let output = self.cli().run(command, timeout)?;
+        self.check_output(command, &output)?;
+
+        Ok(output)
+    }
+
+    /// Re-establishes a dropped connection and restores the session.
+    fn restore(&mut self) -> Result<(), Error> {
+        self.cli().connection.reconnect()?;
+        self.initialize()?;
+        self.restore_mode()
+    }
+
+    /// Executes a command, reconnecting and retrying once if the connection was lost.
+    fn dispatch(&mut self, command: &str, timeout: Option<Duration>) -> Result<String, Error> {
+        match self.run_command(command, timeout) {
+            Err(e)
+                if self.cli().connection.can_reconnect() && !self.cli().connection.is_alive() =>
+            {
+                log::debug!(
+                    "Connection to {} lost while executing '{}': {}",
+                    self.cli().hostname.as_deref().unwrap_or("device"),
+                    command,
+                    e
+                );
+
+                self.restore()?;
+                self.run_command(command, timeout)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Removes the echoed command line from the start of an output, whichever line
+/// ending the device echoes.
+pub(crate) fn strip_echo(command: &str, output: &str) -> String {
+    output
+        .strip_prefix(command)
+        .and_then(|rest| {
+            ["\r\n", "\n\r", "\n"]
+                .iter()
+                .find_map(|ending| rest.strip_prefix(ending))
+        })
+        .unwrap_or(output)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: CliProfile = CliProfile {
+        prompt: r"[\w.-]+[>#]$",
+        errors: &["% Invalid input"],
+        failures: &["commit failed"],
+        ..CliProfile::DEFAULT
+    };
+
+    #[test]
+    fn test_check() {
+        assert!(PROFILE.check("show version", "Version 1.0\r\n").is_ok());
+
+        let error = PROFILE
+            .check("show verison", "show verison\r\n% Invalid input at '^'\r\n")
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::CommandExecution(CommandError::InvalidInput { .. })
+        ));
+
+        let error = PROFILE
+            .check(
+                "commit",
+                "commit\r\n  error: commit failed: (statements constraint check)\r\n",
+            )
+            .unwrap_err();
+        match error {
+            Error::CommandExecution(CommandError::Generic { message, .. }) => {
+                assert_eq!(
+                    message,
+                    "error: commit failed: (statements constraint check)"
+                )
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_strip_echo() {
+        assert_eq!(
+            strip_echo("show clock", "show clock\r\n12:00:00\r\n"),
+            "12:00:00\r\n"
+        );
+        assert_eq!(
+            strip_echo("show clock", "show clock\n\r12:00:00"),
+            "12:00:00"
+        );
+        assert_eq!(strip_echo("show clock", "show clock\n12:00:00"), "12:00:00");
+        assert_eq!(strip_echo("show", "show clock\r\n"), "show clock\r\n");
+        assert_eq!(strip_echo("show clock", "12:00:00"), "12:00:00");
+    }
+}
@@ -27,3 +27,53 @@ impl Drop for ConfigurationMode<'_> {
         let _ = self.session.exit();
     }
 }
+
+/// Executes commands in a view below the current one, like `interface ...` from the
+/// system view, and quits it again even if a command fails, so exiting the
+/// configuration mode starts from the view it was entered in.
+pub(crate) fn execute_in_view(
+    config: &mut (dyn ConfigSession + '_),
+    view: &str,
+    commands: &[String],
+) -> Result<(), Error> {
+    config.execute(view)?;
+
+    let result = commands
+        .iter()
+        .try_for_each(|command| config.execute(command).map(drop));
+    let quit = config.execute("quit");
+
+    result.and(quit.map(drop))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CommandError;
+
+    /// Records the commands, failing the ones starting with `bad`.
+    struct Recorder(Vec<String>);
+
+    impl ConfigSession for Recorder {
+        fn execute(&mut self, command: &str) -> Result<String, Error> {
+            self.0.push(command.to_string());
+
+            if command.starts_with("bad") {
+                return Err(Error::CommandExecution(CommandError::InvalidInput {
+                    command: command.to_string(),
+                }));
+            }
+
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn test_execute_in_view() {
+        let commands = ["bad command".to_string(), "description uplink".to_string()];
+        let mut recorder = Recorder(Vec::new());
+
+        assert!(execute_in_view(&mut recorder, "interface GE1/0/1", &commands).is_err());
+        assert_eq!(recorder.0, ["interface GE1/0/1", "bad command", "quit"]);
+    }
+}
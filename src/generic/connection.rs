@@ -1,13 +1,23 @@
+use std::borrow::Cow;
+use std::fmt;
 use std::io::{self, Read, Write};
-use std::net::{TcpStream, ToSocketAddrs};
-use std::time::Duration;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, LazyLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use encoding_rs::{Encoding, UTF_8};
 use log::debug;
 use regex::Regex;
-use ssh2::{Channel, MethodType, Session};
+use ssh2::{Channel, KeyboardInteractivePrompt, MethodType, Prompt, Session};
 
-use crate::error::Error;
+use super::socket::{self, Bridge};
+use super::ssh_config::{local_user, JumpHost};
+use crate::error::{CommandError, Error};
+use crate::{ConnectConfig, PrivateKey};
 
 /// Trait for establishing and interacting with network connections.
 pub trait Connection {
@@ -19,37 +29,316 @@ pub trait Connection {
         username: Option<&str>,
         password: Option<&str>,
         encoding: &'static Encoding,
+        config: &ConnectConfig<'_>,
     ) -> Result<Self::ConnectionHandler, Error>;
 
     /// Reads output until a prompt matching the provided regex is found.
     fn read(&mut self, prompt: &Regex) -> Result<String, Error>;
 
+    /// Reads the output after login until the prompt, answering the forced
+    /// password change dialog of first logins if configured.
+    fn login(&mut self, prompt: &Regex) -> Result<String, Error>;
+
     /// Executes a command and returns the output until the prompt is matched.
     fn execute(&mut self, command: &str, prompt: &Regex) -> Result<String, Error>;
+
+    /// Executes a command, failing with `Error::Timeout` if the prompt is not matched in time.
+    fn execute_with_timeout(
+        &mut self,
+        command: &str,
+        prompt: &Regex,
+        timeout: Duration,
+    ) -> Result<String, Error>;
+
+    /// Executes a command non-interactively on a dedicated exec channel, bypassing the
+    /// shell and prompt detection. Drivers opt into it per command for platforms that
+    /// handle it more reliably than prompt scraping.
+    fn exec(&mut self, command: &str) -> Result<String, Error>;
+
+    /// Changes the terminal dimensions after connecting, in characters.
+    fn resize_pty(&mut self, width: u32, height: u32) -> Result<(), Error>;
+
+    /// Checks whether the connection is still usable.
+    fn is_alive(&mut self) -> bool;
+
+    /// Whether a reconnect policy was configured for this connection.
+    fn can_reconnect(&self) -> bool;
+
+    /// Re-establishes the connection with the original address, credentials and settings.
+    fn reconnect(&mut self) -> Result<(), Error>;
+}
+
+/// SSH algorithm preferences, each a comma separated list in order of preference.
+///
+/// Unset entries keep the libssh2 defaults, except the host key which defaults to `ssh-rsa`.
+#[derive(Debug, Clone, Default)]
+pub struct Algorithms {
+    pub kex: Option<String>,
+    pub host_key: Option<String>,
+    pub cipher: Option<String>,
+    pub mac: Option<String>,
+}
+
+impl Algorithms {
+    const DEFAULT_HOST_KEY: &'static str = "ssh-rsa";
+
+    /// Applies the preferences to the session, must be called before the handshake.
+    fn apply(&self, sess: &Session) -> Result<(), Error> {
+        let prefs = [
+            (MethodType::Kex, self.kex.as_deref()),
+            (
+                MethodType::HostKey,
+                Some(self.host_key.as_deref().unwrap_or(Self::DEFAULT_HOST_KEY)),
+            ),
+            (MethodType::CryptCs, self.cipher.as_deref()),
+            (MethodType::CryptSc, self.cipher.as_deref()),
+            (MethodType::MacCs, self.mac.as_deref()),
+            (MethodType::MacSc, self.mac.as_deref()),
+        ];
+
+        for (method_type, pref) in prefs {
+            if let Some(pref) = pref {
+                sess.method_pref(method_type, pref)
+                    .map_err(|e| Error::Generic(e.into()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// SSH keepalive settings, keeping idle sessions open through stateful firewalls.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// Interval between two keepalive messages.
+    pub interval: Duration,
+    /// Consecutive failed keepalives after which the connection is considered dead.
+    pub max_missed: u32,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            max_missed: 3,
+        }
+    }
+}
+
+/// Pseudo terminal requested for the interactive shell.
+#[derive(Debug, Clone)]
+pub struct Pty {
+    pub term: String,
+    /// Terminal dimensions in characters(width, height), the server default applies if unset.
+    pub size: Option<(u32, u32)>,
+}
+
+impl Default for Pty {
+    fn default() -> Self {
+        Self {
+            term: "vt100".to_string(),
+            size: None,
+        }
+    }
+}
+
+/// Write pacing for devices dropping characters of commands sent as one burst.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pacing {
+    /// Minimum delay between two commands.
+    pub command_delay: Duration,
+    /// Writes commands one character at a time with this delay in between.
+    pub char_delay: Option<Duration>,
+}
+
+/// Reconnect policy applied when the connection drops.
+#[derive(Debug, Clone, Copy)]
+pub struct Reconnect {
+    /// Number of connection attempts before giving up.
+    pub max_attempts: u32,
+    /// Delay between two connection attempts.
+    pub delay: Duration,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Background thread sending keepalives, stopped when the connection is dropped.
+struct KeepaliveWorker {
+    _stop: Sender<()>,
+    alive: Arc<AtomicBool>,
+}
+
+impl KeepaliveWorker {
+    fn spawn(sess: Session, keepalive: Keepalive) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let alive = Arc::new(AtomicBool::new(true));
+        let flag = alive.clone();
+
+        sess.set_keepalive(true, keepalive.interval.as_secs().max(1) as u32);
+
+        thread::spawn(move || {
+            let mut missed = 0;
+
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(keepalive.interval) {
+                match sess.keepalive_send() {
+                    Ok(_) => missed = 0,
+                    Err(e) => {
+                        missed += 1;
+                        debug!(
+                            "Keepalive failed({}/{}): {}",
+                            missed, keepalive.max_missed, e
+                        );
+
+                        if missed >= keepalive.max_missed {
+                            flag.store(false, Ordering::SeqCst);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        KeepaliveWorker { _stop: stop, alive }
+    }
+}
+
+/// Answers the forced password change dialog(old/new/confirm) of first logins.
+#[derive(Debug, Clone)]
+pub struct PasswordChange {
+    pub new_password: String,
+}
+
+/// Password change dialog steps seen on Huawei/H3C devices, confirmation first
+/// as it usually contains "new password" too.
+const PASSWORD_CHANGE_PATTERNS: [(PasswordChangeStep, &str); 4] = [
+    (
+        PasswordChangeStep::Accept,
+        r"(?i)change (it )?now\?\s*\[Y/N\]:?\s*$",
+    ),
+    (
+        PasswordChangeStep::Confirm,
+        r"(?i)(confirm|re-?enter|retype)[a-z ]*password:?\s*$",
+    ),
+    (PasswordChangeStep::New, r"(?i)new password:?\s*$"),
+    (
+        PasswordChangeStep::Old,
+        r"(?i)(old|current|original) password:?\s*$",
+    ),
+];
+
+/// The password change dialog steps, compiled on first use.
+static PASSWORD_CHANGE_DIALOG: LazyLock<Vec<(PasswordChangeStep, Regex)>> = LazyLock::new(|| {
+    PASSWORD_CHANGE_PATTERNS
+        .iter()
+        .map(|&(step, pattern)| (step, Regex::new(pattern).expect("Invalid dialog regex")))
+        .collect()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PasswordChangeStep {
+    Accept,
+    Old,
+    New,
+    Confirm,
+}
+
+/// Callback answering keyboard-interactive authentication prompts (OTP, banner acknowledgement...).
+///
+/// It is called once per prompt with the server instructions, the prompt text and
+/// whether the answer may be echoed, and returns the response to send.
+#[derive(Clone)]
+pub struct KeyboardInteractive(Arc<PromptFn>);
+
+type PromptFn = dyn Fn(&str, &str, bool) -> String + Send + Sync;
+
+impl KeyboardInteractive {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &str, bool) -> String + Send + Sync + 'static,
+    {
+        KeyboardInteractive(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for KeyboardInteractive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyboardInteractive(..)")
+    }
+}
+
+/// Adapts the configured handler, or answers every prompt with the password if none is set.
+struct KeyboardInteractiveResponder<'a> {
+    handler: Option<&'a KeyboardInteractive>,
+    password: &'a str,
+}
+
+impl KeyboardInteractivePrompt for KeyboardInteractiveResponder<'_> {
+    fn prompt<'b>(
+        &mut self,
+        _username: &str,
+        instructions: &str,
+        prompts: &[Prompt<'b>],
+    ) -> Vec<String> {
+        prompts
+            .iter()
+            .map(|prompt| {
+                debug!("Keyboard-interactive prompt: {}", prompt.text);
+
+                match self.handler {
+                    Some(handler) => (handler.0)(instructions, &prompt.text, prompt.echo),
+                    None => self.password.to_string(),
+                }
+            })
+            .collect()
+    }
 }
 
 /// SSH connection implementation for network devices.
 pub struct SSHConnection {
-    #[allow(dead_code)]
     sess: Session,
     channel: Channel,
     encoding: &'static Encoding,
+    keepalive: Option<Arc<KeepaliveWorker>>,
+    addrs: Vec<SocketAddr>,
+    username: String,
+    password: String,
+    config: ConnectConfig<'static>,
+    last_send: Option<Instant>,
+    /// Thread pumping a caller-provided stream, dropped after the session.
+    bridge: Option<Arc<Bridge>>,
 }
 
 impl SSHConnection {
+    const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Converts a timeout to milliseconds as expected by libssh2, where 0 means no timeout.
+    fn timeout_ms(timeout: Option<Duration>) -> u32 {
+        timeout.map_or(0, |t| t.as_millis().clamp(1, u32::MAX as u128) as u32)
+    }
+
     /// Establishes a TCP connection and initializes an SSH session.
     fn establish_connection<A: ToSocketAddrs>(
         addr: A,
         timeout: Option<Duration>,
+        config: &ConnectConfig<'_>,
     ) -> Result<Session, Error> {
         let mut last_error = None;
         let mut tcp = None;
 
         for addr in addr.to_socket_addrs().map_err(Error::Generic)? {
-            let result = if let Some(timeout) = timeout {
+            let result = if let Some(bind) = &config.bind_address {
+                socket::connect_from(bind, &addr, timeout)
+            } else if let Some(timeout) = timeout {
                 TcpStream::connect_timeout(&addr, timeout)
             } else {
-                TcpStream::connect(&addr)
+                TcpStream::connect(addr)
             };
 
             match result {
@@ -66,21 +355,140 @@ impl SSHConnection {
 
         let tcp = tcp.ok_or_else(|| {
             last_error.map_or_else(
-                || {
-                    Error::Generic(io::Error::new(
-                        io::ErrorKind::Other,
-                        "No socket address was supplied in addr",
-                    ))
-                },
-                |e| Error::Generic(e),
+                || Error::Generic(io::Error::other("No socket address was supplied in addr")),
+                Error::Generic,
             )
         })?;
 
-        let mut sess = Session::new().map_err(|e| Error::Generic(e.into()))?;
-        sess.set_timeout(60_000);
+        Self::handshake(tcp, config)
+    }
+
+    /// Establishes the SSH session, through the configured jump hosts if any.
+    ///
+    /// The target address is resolved locally and reached from the last jump host.
+    fn establish(addrs: &[SocketAddr], config: &ConnectConfig<'_>) -> Result<Session, Error> {
+        let Some((first, rest)) = config.proxy_jump.split_first() else {
+            return Self::establish_connection(addrs, config.connect_timeout, config);
+        };
+
+        debug!("Connecting through jump host {}:{}", first.host, first.port);
+
+        let mut jump = Self::establish_connection(
+            (first.host.as_str(), first.port),
+            config.connect_timeout,
+            config,
+        )?;
+        Self::authenticate_jump(&jump, first, config)?;
+
+        for hop in rest {
+            debug!("Connecting through jump host {}:{}", hop.host, hop.port);
+
+            let tcp = Self::tunnel(jump, &hop.host, hop.port)?;
+            jump = Self::handshake(tcp, config)?;
+            Self::authenticate_jump(&jump, hop, config)?;
+        }
+
+        let mut last_error = None;
 
-        sess.method_pref(MethodType::HostKey, "ssh-rsa")
+        for addr in addrs {
+            match Self::tunnel(jump.clone(), &addr.ip().to_string(), addr.port()) {
+                Ok(tcp) => return Self::handshake(tcp, config),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            Error::Generic(io::Error::other("No socket address was supplied in addr"))
+        }))
+    }
+
+    /// Opens a direct-tcpip channel from the jump session and bridges it to a
+    /// loopback socket, pumped by a background thread owning the jump session.
+    fn tunnel(jump: Session, host: &str, port: u16) -> Result<TcpStream, Error> {
+        let mut channel = jump
+            .channel_direct_tcpip(host, port, None)
             .map_err(|e| Error::Generic(e.into()))?;
+        let (local, mut remote) = socket::loopback_pair().map_err(Error::Generic)?;
+
+        remote.set_nonblocking(true).map_err(Error::Generic)?;
+        jump.set_blocking(false);
+
+        thread::spawn(move || {
+            let mut buf = [0u8; 16 * 1024];
+            let mut to_channel: Vec<u8> = Vec::new();
+            let mut to_socket: Vec<u8> = Vec::new();
+
+            let would_block = |e: &io::Error| e.kind() == io::ErrorKind::WouldBlock;
+
+            loop {
+                let mut idle = true;
+
+                if to_channel.is_empty() {
+                    match remote.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(size) => to_channel.extend_from_slice(&buf[..size]),
+                        Err(ref e) if would_block(e) => {}
+                        Err(_) => break,
+                    }
+                }
+
+                if !to_channel.is_empty() {
+                    match channel.write(&to_channel) {
+                        Ok(size) => {
+                            to_channel.drain(..size);
+                            idle = false;
+                        }
+                        Err(ref e) if would_block(e) => {}
+                        Err(_) => break,
+                    }
+                }
+
+                if to_socket.is_empty() {
+                    match channel.read(&mut buf) {
+                        Ok(0) if channel.eof() => break,
+                        Ok(0) => {}
+                        Ok(size) => to_socket.extend_from_slice(&buf[..size]),
+                        Err(ref e) if would_block(e) => {}
+                        Err(_) => break,
+                    }
+                }
+
+                if !to_socket.is_empty() {
+                    match remote.write(&to_socket) {
+                        Ok(size) => {
+                            to_socket.drain(..size);
+                            idle = false;
+                        }
+                        Err(ref e) if would_block(e) => {}
+                        Err(_) => break,
+                    }
+                }
+
+                if idle {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+
+            debug!("Jump host tunnel closed");
+            let _ = remote.shutdown(std::net::Shutdown::Both);
+            drop(channel);
+            drop(jump);
+        });
+
+        Ok(local)
+    }
+
+    /// Initializes an SSH session over an established TCP connection.
+    fn handshake(tcp: TcpStream, config: &ConnectConfig<'_>) -> Result<Session, Error> {
+        let mut sess = Session::new().map_err(|e| Error::Generic(e.into()))?;
+        sess.set_timeout(Self::timeout_ms(Some(
+            config
+                .session_timeout
+                .unwrap_or(Self::DEFAULT_SESSION_TIMEOUT),
+        )));
+
+        config.algorithms.apply(&sess)?;
+        sess.set_compress(config.compression);
 
         sess.set_tcp_stream(tcp);
         sess.handshake().map_err(|e| Error::Generic(e.into()))?;
@@ -88,44 +496,111 @@ impl SSHConnection {
         Ok(sess)
     }
 
-    /// Creates a new SSH channel session.
-    fn make_channel_session(
-        session: Session,
-        encoding: &'static Encoding,
-    ) -> Result<SSHConnection, Error> {
-        let mut channel = session
-            .channel_session()
-            .map_err(|e| Error::Generic(e.into()))?;
-        channel
-            .request_pty("vt100", None, None)
-            .map_err(|e| Error::Generic(e.into()))?;
-        channel.shell().map_err(|e| Error::Generic(e.into()))?;
+    /// Authenticates the session, bounded by the configured authentication timeout.
+    fn authenticate(
+        sess: &Session,
+        username: &str,
+        password: &str,
+        config: &ConnectConfig<'_>,
+    ) -> Result<(), Error> {
+        Self::with_auth_timeout(sess, config, || {
+            Self::authenticate_methods(sess, username, password, config)
+        })
+    }
 
-        Ok(SSHConnection {
-            sess: session,
-            channel,
-            encoding,
+    /// Authenticates on a jump host with its identity files, the configured private key
+    /// and the ssh-agent only, like OpenSSH with its own host entry. The device password
+    /// is never sent to a jump host.
+    fn authenticate_jump(
+        sess: &Session,
+        hop: &JumpHost,
+        config: &ConnectConfig<'_>,
+    ) -> Result<(), Error> {
+        let username = hop.user.clone().or_else(local_user).ok_or_else(|| {
+            Error::Generic(io::Error::other(format!(
+                "No user for jump host {}",
+                hop.host
+            )))
+        })?;
+
+        Self::with_auth_timeout(sess, config, || {
+            let key = config.private_key.as_ref();
+            if Self::authenticate_keys(sess, &username, &hop.identity_files, key, true) {
+                return Ok(());
+            }
+
+            Err(Error::AuthenticationFailed {
+                user: username.clone(),
+            })
         })
     }
-}
 
-impl Connection for SSHConnection {
-    type ConnectionHandler = SSHConnection;
+    fn with_auth_timeout(
+        sess: &Session,
+        config: &ConnectConfig<'_>,
+        authenticate: impl FnOnce() -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let session_timeout = sess.timeout();
 
-    fn connect<A: ToSocketAddrs>(
-        addr: A,
-        username: Option<&str>,
-        password: Option<&str>,
-        encoding: &'static Encoding,
-    ) -> Result<SSHConnection, Error> {
-        let username = username.unwrap_or("admin");
-        let password = password.unwrap_or("admin");
+        if config.auth_timeout.is_some() {
+            sess.set_timeout(Self::timeout_ms(config.auth_timeout));
+        }
 
-        let sess = Self::establish_connection(addr, None)?;
-        sess.userauth_password(username, password)
-            .map_err(|_| Error::AuthenticationFailed {
-                user: username.to_string(),
-            })?;
+        let result = authenticate();
+        sess.set_timeout(session_timeout);
+
+        result
+    }
+
+    /// Tries the configured private key and the ssh-agent before the password,
+    /// then keyboard-interactive.
+    fn authenticate_methods(
+        sess: &Session,
+        username: &str,
+        password: &str,
+        config: &ConnectConfig<'_>,
+    ) -> Result<(), Error> {
+        let methods = match sess.auth_methods(username) {
+            Ok(methods) => Some(methods.to_string()),
+            Err(e) => {
+                debug!("Failed to query authentication methods: {}", e);
+                None
+            }
+        };
+
+        if sess.authenticated() {
+            return Ok(());
+        }
+
+        let offered = |method: &str| {
+            methods
+                .as_deref()
+                .is_none_or(|methods| methods.split(',').any(|m| m == method))
+        };
+
+        let key = config.private_key.as_ref();
+        if Self::authenticate_keys(sess, username, &[], key, config.use_agent) {
+            return Ok(());
+        }
+
+        if offered("password") {
+            match sess.userauth_password(username, password) {
+                Ok(_) if sess.authenticated() => return Ok(()),
+                Ok(_) => debug!("Password authentication incomplete, trying next method"),
+                Err(e) => debug!("Password authentication failed: {}", e),
+            }
+        }
+
+        if offered("keyboard-interactive") {
+            let mut responder = KeyboardInteractiveResponder {
+                handler: config.keyboard_interactive.as_ref(),
+                password,
+            };
+
+            if let Err(e) = sess.userauth_keyboard_interactive(username, &mut responder) {
+                debug!("Keyboard-interactive authentication failed: {}", e);
+            }
+        }
 
         if !sess.authenticated() {
             return Err(Error::AuthenticationFailed {
@@ -133,36 +608,155 @@ impl Connection for SSHConnection {
             });
         }
 
-        Self::make_channel_session(sess, encoding)
+        Ok(())
     }
 
-    fn read(&mut self, prompt: &Regex) -> Result<String, Error> {
+    /// Tries the existing identity files, the private key and the ssh-agent in turn,
+    /// returning whether one was accepted.
+    fn authenticate_keys(
+        sess: &Session,
+        username: &str,
+        identity_files: &[PathBuf],
+        private_key: Option<&PrivateKey>,
+        use_agent: bool,
+    ) -> bool {
+        for path in identity_files.iter().filter(|path| path.exists()) {
+            match sess.userauth_pubkey_file(username, None, path, None) {
+                Ok(_) if sess.authenticated() => return true,
+                Ok(_) => debug!("Public key authentication incomplete, trying next method"),
+                Err(e) => debug!("Public key authentication with {:?} failed: {}", path, e),
+            }
+        }
+
+        if let Some(key) = private_key {
+            let result = match key {
+                PrivateKey::File { path, passphrase } => {
+                    sess.userauth_pubkey_file(username, None, path, passphrase.as_deref())
+                }
+                PrivateKey::Memory { pem, passphrase } => {
+                    sess.userauth_pubkey_memory(username, None, pem, passphrase.as_deref())
+                }
+            };
+
+            match result {
+                Ok(_) if sess.authenticated() => return true,
+                Ok(_) => debug!("Public key authentication incomplete, trying next method"),
+                Err(e) => debug!("Public key authentication failed: {}", e),
+            }
+        }
+
+        if use_agent {
+            match Self::authenticate_agent(sess, username) {
+                Ok(_) if sess.authenticated() => return true,
+                Ok(_) => debug!("Agent authentication incomplete, trying next method"),
+                Err(e) => debug!("Agent authentication failed: {}", e),
+            }
+        }
+
+        false
+    }
+
+    /// Reads stdout and stderr of an exec channel together until both are drained, as
+    /// unread data on one stream holds back the window the streams share.
+    fn read_exec_output(sess: &Session, channel: &Channel) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut buf = [0u8; 4096];
+
+        sess.set_blocking(false);
+        let result = loop {
+            let mut received = false;
+            let mut error = None;
+
+            for (stream_id, output) in [(0, &mut stdout), (1, &mut stderr)] {
+                match channel.stream(stream_id).read(&mut buf) {
+                    Ok(size) => {
+                        output.extend_from_slice(&buf[..size]);
+                        received |= size > 0;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => error = Some(e),
+                }
+            }
+
+            if let Some(e) = error {
+                break Err(e);
+            }
+            if !received {
+                if channel.eof() {
+                    break Ok(());
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+        sess.set_blocking(true);
+
+        result.map(|_| (stdout, stderr))
+    }
+
+    /// Tries every identity held by the running ssh-agent until one is accepted.
+    fn authenticate_agent(sess: &Session, username: &str) -> Result<(), ssh2::Error> {
+        let mut agent = sess.agent()?;
+        agent.connect()?;
+        agent.list_identities()?;
+
+        let mut last_error = None;
+        for identity in agent.identities()? {
+            match agent.userauth(username, &identity) {
+                Ok(_) => {
+                    debug!("Authenticated with agent identity {}", identity.comment());
+                    last_error = None;
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let _ = agent.disconnect();
+
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads output until the prompt is matched, or fails with `Error::Timeout` once
+    /// the optional timeout elapses.
+    fn read_until(&mut self, prompt: &Regex, timeout: Option<Duration>) -> Result<String, Error> {
         debug!("Reading from SSH channel...");
 
+        let start = Instant::now();
+        let session_timeout = Duration::from_millis(self.sess.timeout() as u64);
         let mut output = String::new();
         let mut buf = [0u8; 1024];
 
         loop {
+            if let Some(timeout) = timeout {
+                let remaining = timeout.saturating_sub(start.elapsed());
+
+                if remaining.is_zero() {
+                    return Err(Error::Timeout {
+                        command: String::new(),
+                        elapsed: start.elapsed(),
+                        partial_output: output,
+                    });
+                }
+
+                let wait = if session_timeout.is_zero() {
+                    remaining
+                } else {
+                    remaining.min(session_timeout)
+                };
+                self.sess.set_timeout(Self::timeout_ms(Some(wait)));
+            }
+
             match self.channel.read(&mut buf) {
                 Ok(0) => {
                     debug!("End of stream reached");
                     break;
                 }
                 Ok(size) => {
-                    let str = if self.encoding == UTF_8 {
-                        String::from_utf8_lossy(&buf[..size])
-                    } else {
-                        let (cow, _, had_errors) = self.encoding.decode(&buf[..size]);
-
-                        if had_errors {
-                            return Err(Error::EncodingError {
-                                operation: "decode".to_string(),
-                                encoding_name: self.encoding.name().to_string(),
-                            });
-                        }
-
-                        cow
-                    };
+                    let str = self.decode(&buf[..size])?;
 
                     debug!("Read: {}", str);
                     output.push_str(&str);
@@ -173,6 +767,11 @@ impl Connection for SSHConnection {
                     }
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
+                    // With a command timeout, silence only ends the read once it elapses
+                    if timeout.is_some() {
+                        continue;
+                    }
+
                     debug!("Read timeout, assuming no more data");
                     break;
                 }
@@ -183,38 +782,421 @@ impl Connection for SSHConnection {
         Ok(output)
     }
 
-    fn execute(&mut self, command: &str, prompt: &Regex) -> Result<String, Error> {
+    /// Decodes device output with the connection encoding.
+    fn decode<'b>(&self, bytes: &'b [u8]) -> Result<Cow<'b, str>, Error> {
+        if self.encoding == UTF_8 {
+            return Ok(String::from_utf8_lossy(bytes));
+        }
+
+        let (cow, _, had_errors) = self.encoding.decode(bytes);
+
+        if had_errors {
+            return Err(Error::EncodingError {
+                operation: "decode".to_string(),
+                encoding_name: self.encoding.name().to_string(),
+            });
+        }
+
+        Ok(cow)
+    }
+
+    /// Encodes input for the device with the connection encoding.
+    fn encode<'b>(&self, text: &'b str) -> Result<Cow<'b, [u8]>, Error> {
+        if self.encoding == UTF_8 {
+            return Ok(Cow::Borrowed(text.as_bytes()));
+        }
+
+        let (cow, _, had_errors) = self.encoding.encode(text);
+
+        if had_errors {
+            return Err(Error::EncodingError {
+                operation: "encode".to_string(),
+                encoding_name: self.encoding.name().to_string(),
+            });
+        }
+
+        Ok(cow)
+    }
+
+    /// Writes a command followed by a newline to the channel, paced as configured.
+    fn send(&mut self, command: &str) -> Result<(), Error> {
         debug!("Executing command: {}", command);
 
-        let command_with_newline = format!("{}\n", command);
-        let command_bytes = if self.encoding == UTF_8 {
-            command_with_newline.as_bytes()
-        } else {
-            let (cow, _, had_errors) = self.encoding.encode(&command_with_newline);
+        if self.channel.eof() {
+            return Err(Error::Disconnected);
+        }
+
+        let pacing = self.config.pacing.unwrap_or_default();
+
+        if let Some(last_send) = self.last_send {
+            let elapsed = last_send.elapsed();
 
-            if had_errors {
-                return Err(Error::EncodingError {
-                    operation: "encode".to_string(),
-                    encoding_name: self.encoding.name().to_string(),
-                });
+            if elapsed < pacing.command_delay {
+                thread::sleep(pacing.command_delay - elapsed);
             }
+        }
 
-            &cow.into_owned()
+        let command_with_newline = format!("{}\n", command);
+        let chunks: Vec<Cow<[u8]>> = match pacing.char_delay {
+            Some(_) => {
+                let mut buf = [0u8; 4];
+                command_with_newline
+                    .chars()
+                    .map(|c| {
+                        self.encode(c.encode_utf8(&mut buf))
+                            .map(|b| Cow::Owned(b.into_owned()))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            None => vec![self.encode(&command_with_newline)?],
         };
 
-        self.channel
-            .write_all(command_bytes)
-            .and_then(|_| self.channel.flush())
-            .map_err(|e| {
-                Error::CommandExecution(crate::error::CommandError::Generic {
-                    command: command.to_owned(),
-                    message: e.to_string(),
-                })
-            })?;
-
-        let output = self.read(prompt)?;
+        for chunk in chunks {
+            self.channel
+                .write_all(&chunk)
+                .and_then(|_| self.channel.flush())
+                .map_err(|e| {
+                    Error::CommandExecution(CommandError::Generic {
+                        command: command.to_owned(),
+                        message: e.to_string(),
+                    })
+                })?;
+
+            if let Some(char_delay) = pacing.char_delay {
+                thread::sleep(char_delay);
+            }
+        }
+
+        self.last_send = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Creates a new SSH channel session.
+    fn make_channel_session(
+        session: Session,
+        config: &ConnectConfig<'_>,
+    ) -> Result<(Channel, Option<Arc<KeepaliveWorker>>), Error> {
+        let channel = Self::open_shell(&session, config)?;
+
+        let keepalive = config
+            .keepalive
+            .map(|keepalive| Arc::new(KeepaliveWorker::spawn(session.clone(), keepalive)));
+
+        Ok((channel, keepalive))
+    }
+
+    /// Opens a channel with an interactive shell on the session.
+    fn open_shell(session: &Session, config: &ConnectConfig<'_>) -> Result<Channel, Error> {
+        let pty = config.pty.clone().unwrap_or_default();
+        let dim = pty.size.map(|(width, height)| (width, height, 0, 0));
+
+        let mut channel = session
+            .channel_session()
+            .map_err(|e| Error::Generic(e.into()))?;
+        channel
+            .request_pty(&pty.term, None, dim)
+            .map_err(|e| Error::Generic(e.into()))?;
+        channel.shell().map_err(|e| Error::Generic(e.into()))?;
+
+        Ok(channel)
+    }
+}
+
+impl SSHConnection {
+    /// Establishes a connection over a caller-provided stream, e.g. a TLS-wrapped
+    /// socket or a tunnel, instead of a plain `TcpStream`.
+    ///
+    /// The stream is pumped by one thread, so it must be non-blocking or time out
+    /// reads, e.g. with `set_read_timeout` on its socket. The thread stops when the
+    /// connection is dropped.
+    ///
+    /// Such a connection can't be re-established, the reconnect policy is ignored.
+    pub fn connect_with_stream<S: Read + Write + Send + 'static>(
+        stream: S,
+        username: Option<&str>,
+        password: Option<&str>,
+        encoding: &'static Encoding,
+        config: &ConnectConfig<'_>,
+    ) -> Result<SSHConnection, Error> {
+        let (tcp, bridge) = socket::bridge(stream).map_err(Error::Generic)?;
+        let sess = Self::handshake(tcp, config)?;
+
+        let mut connection = Self::open(sess, Vec::new(), username, password, encoding, config)?;
+        connection.bridge = Some(Arc::new(bridge));
+
+        Ok(connection)
+    }
+
+    /// Opens another shell channel on the same authenticated session, without a new
+    /// TCP connection, handshake and authentication.
+    ///
+    /// Channels share the session lock: a blocking read on one channel delays the
+    /// others until data arrives or the session timeout elapses.
+    pub fn clone_channel(&self) -> Result<SSHConnection, Error> {
+        let channel = Self::open_shell(&self.sess, &self.config)?;
+
+        Ok(SSHConnection {
+            sess: self.sess.clone(),
+            channel,
+            encoding: self.encoding,
+            keepalive: self.keepalive.clone(),
+            addrs: self.addrs.clone(),
+            username: self.username.clone(),
+            password: self.password.clone(),
+            config: self.config.clone(),
+            last_send: None,
+            bridge: self.bridge.clone(),
+        })
+    }
+
+    /// Authenticates the session and opens the interactive shell channel.
+    fn open(
+        sess: Session,
+        addrs: Vec<SocketAddr>,
+        username: Option<&str>,
+        password: Option<&str>,
+        encoding: &'static Encoding,
+        config: &ConnectConfig<'_>,
+    ) -> Result<SSHConnection, Error> {
+        let username = username.unwrap_or("admin");
+        let password = password.unwrap_or("admin");
+
+        Self::authenticate(&sess, username, password, config)?;
+        let (channel, keepalive) = Self::make_channel_session(sess.clone(), config)?;
+
+        Ok(SSHConnection {
+            sess,
+            channel,
+            encoding,
+            keepalive,
+            addrs,
+            username: username.to_string(),
+            password: password.to_string(),
+            config: config.to_static(),
+            last_send: None,
+            bridge: None,
+        })
+    }
+}
+
+impl Connection for SSHConnection {
+    type ConnectionHandler = SSHConnection;
+
+    fn connect<A: ToSocketAddrs>(
+        addr: A,
+        username: Option<&str>,
+        password: Option<&str>,
+        encoding: &'static Encoding,
+        config: &ConnectConfig<'_>,
+    ) -> Result<SSHConnection, Error> {
+        let username = username.unwrap_or("admin");
+        let password = password.unwrap_or("admin");
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs().map_err(Error::Generic)?.collect();
+        let sess = Self::establish(&addrs, config)?;
+
+        Self::open(
+            sess,
+            addrs,
+            Some(username),
+            Some(password),
+            encoding,
+            config,
+        )
+    }
+
+    fn read(&mut self, prompt: &Regex) -> Result<String, Error> {
+        self.read_until(prompt, None)
+    }
+
+    fn login(&mut self, prompt: &Regex) -> Result<String, Error> {
+        // Only the stop pattern is built per login, as it contains the driver prompt
+        let patterns: Vec<&str> = std::iter::once(prompt.as_str())
+            .chain(PASSWORD_CHANGE_PATTERNS.iter().map(|(_, pattern)| *pattern))
+            .collect();
+        let stop =
+            Regex::new(&format!("(?:{})", patterns.join(")|(?:"))).expect("Invalid login regex");
+
+        let mut output = String::new();
+
+        // Each dialog step is answered at most a few times, to not loop on rejected passwords
+        for _ in 0..8 {
+            let chunk = self.read(&stop)?;
+            output.push_str(&chunk);
+
+            let Some(step) = PASSWORD_CHANGE_DIALOG
+                .iter()
+                .find(|(_, regex)| regex.is_match(chunk.trim_end_matches(['\r', '\n'])))
+                .map(|(step, _)| *step)
+            else {
+                return Ok(output);
+            };
+
+            debug!("Password change dialog: {:?}", step);
+
+            let new_password = match &self.config.password_change {
+                Some(change) => change.new_password.clone(),
+                None if step == PasswordChangeStep::Accept => {
+                    self.send("N")?;
+                    continue;
+                }
+                None => return Err(Error::PasswordChangeRequired),
+            };
+
+            let answer = match step {
+                PasswordChangeStep::Accept => "Y".to_string(),
+                PasswordChangeStep::Old => self.password.clone(),
+                PasswordChangeStep::New => new_password,
+                PasswordChangeStep::Confirm => {
+                    self.password = new_password.clone();
+                    new_password
+                }
+            };
+
+            self.send(&answer)?;
+        }
+
+        Err(Error::PasswordChangeRequired)
+    }
+
+    fn execute(&mut self, command: &str, prompt: &Regex) -> Result<String, Error> {
+        match self.config.command_timeout {
+            Some(timeout) => self.execute_with_timeout(command, prompt, timeout),
+            None => {
+                self.send(command)?;
+
+                let output = self.read(prompt)?;
+                let trimmed = prompt.replace_all(&output, "").to_string();
+
+                Ok(trimmed)
+            }
+        }
+    }
+
+    fn execute_with_timeout(
+        &mut self,
+        command: &str,
+        prompt: &Regex,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        self.send(command)?;
+
+        let session_timeout = self.sess.timeout();
+        let output = self.read_until(prompt, Some(timeout));
+        self.sess.set_timeout(session_timeout);
+
+        let output = output.map_err(|e| match e {
+            Error::Timeout {
+                elapsed,
+                partial_output,
+                ..
+            } => Error::Timeout {
+                command: command.to_owned(),
+                elapsed,
+                partial_output,
+            },
+            e => e,
+        })?;
         let trimmed = prompt.replace_all(&output, "").to_string();
 
         Ok(trimmed)
     }
+
+    fn is_alive(&mut self) -> bool {
+        if let Some(keepalive) = &self.keepalive {
+            if !keepalive.alive.load(Ordering::SeqCst) {
+                return false;
+            }
+        }
+
+        if self.channel.eof() {
+            return false;
+        }
+
+        self.sess.keepalive_send().is_ok()
+    }
+
+    fn can_reconnect(&self) -> bool {
+        self.config.reconnect.is_some() && !self.addrs.is_empty()
+    }
+
+    fn reconnect(&mut self) -> Result<(), Error> {
+        let policy = self.config.reconnect.unwrap_or_default();
+        let mut last_error = Error::Disconnected;
+
+        for attempt in 1..=policy.max_attempts {
+            debug!("Reconnecting, attempt {}/{}", attempt, policy.max_attempts);
+
+            let result = Self::establish(&self.addrs, &self.config).and_then(|sess| {
+                Self::authenticate(&sess, &self.username, &self.password, &self.config)?;
+                let (channel, keepalive) = Self::make_channel_session(sess.clone(), &self.config)?;
+
+                Ok((sess, channel, keepalive))
+            });
+
+            match result {
+                Ok((sess, channel, keepalive)) => {
+                    self.sess = sess;
+                    self.channel = channel;
+                    self.keepalive = keepalive;
+
+                    return Ok(());
+                }
+                Err(e) => {
+                    debug!("Reconnect failed: {}", e);
+                    last_error = e;
+
+                    if attempt < policy.max_attempts {
+                        thread::sleep(policy.delay);
+                    }
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    fn exec(&mut self, command: &str) -> Result<String, Error> {
+        debug!("Executing command on exec channel: {}", command);
+
+        let command_error = |message: String| {
+            Error::CommandExecution(CommandError::Generic {
+                command: command.to_owned(),
+                message,
+            })
+        };
+
+        let mut channel = self
+            .sess
+            .channel_session()
+            .map_err(|e| Error::Generic(e.into()))?;
+        channel
+            .exec(command)
+            .map_err(|e| command_error(e.to_string()))?;
+
+        let (stdout, stderr) =
+            Self::read_exec_output(&self.sess, &channel).map_err(Error::Generic)?;
+        channel.wait_close().map_err(|e| Error::Generic(e.into()))?;
+
+        let status = channel
+            .exit_status()
+            .map_err(|e| Error::Generic(e.into()))?;
+        if status != 0 {
+            let stderr = self.decode(&stderr)?;
+            let message = match stderr.trim() {
+                "" => format!("Exit status {}", status),
+                stderr => stderr.to_string(),
+            };
+
+            return Err(command_error(message));
+        }
+
+        Ok(self.decode(&stdout)?.into_owned())
+    }
+
+    fn resize_pty(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        self.channel
+            .request_pty_size(width, height, None, None)
+            .map_err(|e| Error::Generic(e.into()))
+    }
 }
@@ -0,0 +1,596 @@
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use encoding_rs::{Encoding, UTF_8};
+use log::debug;
+use regex::Regex;
+
+use super::connection::Connection;
+use super::socket;
+use crate::error::{CommandError, Error};
+use crate::ConnectConfig;
+
+/// Console line settings of a terminal server port(reverse telnet).
+#[derive(Debug, Clone, Copy)]
+pub struct Console {
+    /// Carriage returns sent to wake an idle line before giving up.
+    pub wake_attempts: u32,
+    /// Time to wait for output after each carriage return.
+    pub wake_interval: Duration,
+    /// Negotiate telnet options, disable for raw TCP ports.
+    pub telnet: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            wake_attempts: 5,
+            wake_interval: Duration::from_secs(2),
+            telnet: true,
+        }
+    }
+}
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TelnetState {
+    Data,
+    Command,
+    Option(u8),
+    Subnegotiation,
+    SubnegotiationCommand,
+}
+
+/// Strips telnet commands from the received bytes and produces the replies, refusing
+/// every option but the server echo and suppressed go-ahead of character mode.
+#[derive(Debug)]
+struct Telnet {
+    state: TelnetState,
+}
+
+impl Telnet {
+    fn new() -> Self {
+        Self {
+            state: TelnetState::Data,
+        }
+    }
+
+    /// Returns the data bytes and the negotiation replies to send.
+    fn process(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut data = Vec::with_capacity(bytes.len());
+        let mut replies = Vec::new();
+
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (TelnetState::Data, IAC) => TelnetState::Command,
+                (TelnetState::Data, _) => {
+                    data.push(byte);
+                    TelnetState::Data
+                }
+                (TelnetState::Command, IAC) => {
+                    data.push(IAC);
+                    TelnetState::Data
+                }
+                (TelnetState::Command, DO | DONT | WILL | WONT) => TelnetState::Option(byte),
+                (TelnetState::Command, SB) => TelnetState::Subnegotiation,
+                (TelnetState::Command, _) => TelnetState::Data,
+                (TelnetState::Option(verb), option) => {
+                    let reply = match verb {
+                        DO if option == SUPPRESS_GO_AHEAD => Some(WILL),
+                        DO => Some(WONT),
+                        WILL if option == ECHO || option == SUPPRESS_GO_AHEAD => Some(DO),
+                        WILL => Some(DONT),
+                        _ => None,
+                    };
+
+                    if let Some(reply) = reply {
+                        replies.extend_from_slice(&[IAC, reply, option]);
+                    }
+
+                    TelnetState::Data
+                }
+                (TelnetState::Subnegotiation, IAC) => TelnetState::SubnegotiationCommand,
+                (TelnetState::Subnegotiation, _) => TelnetState::Subnegotiation,
+                (TelnetState::SubnegotiationCommand, SE) => TelnetState::Data,
+                (TelnetState::SubnegotiationCommand, _) => TelnetState::Subnegotiation,
+            };
+        }
+
+        (data, replies)
+    }
+
+    /// Escapes data bytes that would be taken for a telnet command.
+    fn escape(bytes: &[u8]) -> Cow<'_, [u8]> {
+        if !bytes.contains(&IAC) {
+            return Cow::Borrowed(bytes);
+        }
+
+        let mut escaped = Vec::with_capacity(bytes.len() + 1);
+        for &byte in bytes {
+            escaped.push(byte);
+
+            if byte == IAC {
+                escaped.push(IAC);
+            }
+        }
+
+        Cow::Owned(escaped)
+    }
+}
+
+/// Connection to a device console line through a terminal server port, e.g. the
+/// 2001-2048 reverse telnet ports of a Cisco access server.
+///
+/// Such lines usually have no login of their own: the line is woken with carriage
+/// returns and the vendor prompt detection runs as usual. A device login prompt on the
+/// line is answered with the given credentials.
+pub struct ConsoleConnection {
+    stream: TcpStream,
+    telnet: Option<Telnet>,
+    encoding: &'static Encoding,
+    addrs: Vec<SocketAddr>,
+    username: Option<String>,
+    password: Option<String>,
+    config: ConnectConfig<'static>,
+    last_send: Option<Instant>,
+    eof: bool,
+}
+
+impl ConsoleConnection {
+    const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Opens the TCP connection to the terminal server port.
+    fn establish(addrs: &[SocketAddr], config: &ConnectConfig<'_>) -> Result<TcpStream, Error> {
+        let mut last_error = None;
+
+        for addr in addrs {
+            let result = if let Some(bind) = &config.bind_address {
+                socket::connect_from(bind, addr, config.connect_timeout)
+            } else if let Some(timeout) = config.connect_timeout {
+                TcpStream::connect_timeout(addr, timeout)
+            } else {
+                TcpStream::connect(addr)
+            };
+
+            match result {
+                Ok(stream) => {
+                    let timeout = config
+                        .session_timeout
+                        .unwrap_or(Self::DEFAULT_SESSION_TIMEOUT);
+                    stream
+                        .set_read_timeout(Some(timeout).filter(|t| !t.is_zero()))
+                        .map_err(Error::Generic)?;
+                    stream.set_nodelay(true).map_err(Error::Generic)?;
+
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.map_or_else(
+            || Error::Generic(io::Error::other("No socket address was supplied in addr")),
+            Error::Generic,
+        ))
+    }
+
+    fn session_timeout(&self) -> Duration {
+        self.config
+            .session_timeout
+            .unwrap_or(Self::DEFAULT_SESSION_TIMEOUT)
+    }
+
+    /// Reads output until the prompt is matched, or fails with `Error::Timeout` once
+    /// the optional timeout elapses.
+    fn read_until(&mut self, prompt: &Regex, timeout: Option<Duration>) -> Result<String, Error> {
+        debug!("Reading from console line...");
+
+        let start = Instant::now();
+        let session_timeout = self.session_timeout();
+        let mut output = String::new();
+        let mut buf = [0u8; 1024];
+
+        let result = loop {
+            if let Some(timeout) = timeout {
+                let remaining = timeout.saturating_sub(start.elapsed());
+
+                if remaining.is_zero() {
+                    break Err(Error::Timeout {
+                        command: String::new(),
+                        elapsed: start.elapsed(),
+                        partial_output: output,
+                    });
+                }
+
+                let wait = if session_timeout.is_zero() {
+                    remaining
+                } else {
+                    remaining.min(session_timeout)
+                };
+                self.stream
+                    .set_read_timeout(Some(wait))
+                    .map_err(Error::Generic)?;
+            }
+
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    debug!("End of stream reached");
+                    self.eof = true;
+                    break Ok(output);
+                }
+                Ok(size) => {
+                    let data = match &mut self.telnet {
+                        Some(telnet) => {
+                            let (data, replies) = telnet.process(&buf[..size]);
+
+                            if !replies.is_empty() {
+                                self.stream.write_all(&replies).map_err(Error::Generic)?;
+                            }
+
+                            data
+                        }
+                        None => buf[..size].to_vec(),
+                    };
+                    let str = self.decode(&data)?.into_owned();
+
+                    debug!("Read: {}", str);
+                    output.push_str(&str);
+
+                    if prompt.is_match(&str) {
+                        debug!("Prompt found, stopping read");
+                        break Ok(output);
+                    }
+                }
+                Err(ref e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                    ) =>
+                {
+                    // With a command timeout, silence only ends the read once it elapses
+                    if timeout.is_some() {
+                        continue;
+                    }
+
+                    debug!("Read timeout, assuming no more data");
+                    break Ok(output);
+                }
+                Err(e) => break Err(Error::Generic(e)),
+            }
+        };
+
+        if timeout.is_some() {
+            self.stream
+                .set_read_timeout(Some(session_timeout).filter(|t| !t.is_zero()))
+                .map_err(Error::Generic)?;
+        }
+
+        result
+    }
+
+    /// Decodes device output with the connection encoding.
+    fn decode<'b>(&self, bytes: &'b [u8]) -> Result<Cow<'b, str>, Error> {
+        if self.encoding == UTF_8 {
+            return Ok(String::from_utf8_lossy(bytes));
+        }
+
+        let (cow, _, had_errors) = self.encoding.decode(bytes);
+
+        if had_errors {
+            return Err(Error::EncodingError {
+                operation: "decode".to_string(),
+                encoding_name: self.encoding.name().to_string(),
+            });
+        }
+
+        Ok(cow)
+    }
+
+    /// Encodes input for the device with the connection encoding.
+    fn encode<'b>(&self, text: &'b str) -> Result<Cow<'b, [u8]>, Error> {
+        if self.encoding == UTF_8 {
+            return Ok(Cow::Borrowed(text.as_bytes()));
+        }
+
+        let (cow, _, had_errors) = self.encoding.encode(text);
+
+        if had_errors {
+            return Err(Error::EncodingError {
+                operation: "encode".to_string(),
+                encoding_name: self.encoding.name().to_string(),
+            });
+        }
+
+        Ok(cow)
+    }
+
+    /// Writes a command followed by a carriage return to the line, paced as configured.
+    fn send(&mut self, command: &str) -> Result<(), Error> {
+        debug!("Executing command: {}", command);
+
+        if self.eof {
+            return Err(Error::Disconnected);
+        }
+
+        let pacing = self.config.pacing.unwrap_or_default();
+
+        if let Some(last_send) = self.last_send {
+            let elapsed = last_send.elapsed();
+
+            if elapsed < pacing.command_delay {
+                thread::sleep(pacing.command_delay - elapsed);
+            }
+        }
+
+        let command_with_newline = format!("{}\r", command);
+        let chunks: Vec<Vec<u8>> = match pacing.char_delay {
+            Some(_) => {
+                let mut buf = [0u8; 4];
+                command_with_newline
+                    .chars()
+                    .map(|c| self.encode(c.encode_utf8(&mut buf)).map(Cow::into_owned))
+                    .collect::<Result<_, _>>()?
+            }
+            None => vec![self.encode(&command_with_newline)?.into_owned()],
+        };
+
+        for chunk in chunks {
+            let chunk = match self.telnet {
+                Some(_) => Telnet::escape(&chunk).into_owned(),
+                None => chunk,
+            };
+
+            self.stream
+                .write_all(&chunk)
+                .and_then(|_| self.stream.flush())
+                .map_err(|e| {
+                    Error::CommandExecution(CommandError::Generic {
+                        command: command.to_owned(),
+                        message: e.to_string(),
+                    })
+                })?;
+
+            if let Some(char_delay) = pacing.char_delay {
+                thread::sleep(char_delay);
+            }
+        }
+
+        self.last_send = Some(Instant::now());
+
+        Ok(())
+    }
+}
+
+impl Connection for ConsoleConnection {
+    type ConnectionHandler = ConsoleConnection;
+
+    fn connect<A: ToSocketAddrs>(
+        addr: A,
+        username: Option<&str>,
+        password: Option<&str>,
+        encoding: &'static Encoding,
+        config: &ConnectConfig<'_>,
+    ) -> Result<ConsoleConnection, Error> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs().map_err(Error::Generic)?.collect();
+        let stream = Self::establish(&addrs, config)?;
+        let console = config.console.unwrap_or_default();
+
+        Ok(ConsoleConnection {
+            stream,
+            telnet: console.telnet.then(Telnet::new),
+            encoding,
+            addrs,
+            username: username.map(String::from),
+            password: password.map(String::from),
+            config: config.to_static(),
+            last_send: None,
+            eof: false,
+        })
+    }
+
+    fn read(&mut self, prompt: &Regex) -> Result<String, Error> {
+        self.read_until(prompt, None)
+    }
+
+    /// Wakes the line with carriage returns until the prompt shows up, answering a
+    /// device login on the way.
+    fn login(&mut self, prompt: &Regex) -> Result<String, Error> {
+        let username_prompt =
+            Regex::new(r"(?i)(user ?name|login):\s*$").expect("Invalid username regex");
+        let password_prompt = Regex::new(r"(?i)password:\s*$").expect("Invalid password regex");
+        let stop = Regex::new(&format!(
+            "(?:{})|(?:{})|(?:{})",
+            prompt.as_str(),
+            username_prompt.as_str(),
+            password_prompt.as_str()
+        ))
+        .expect("Invalid login regex");
+
+        let console = self.config.console.unwrap_or_default();
+        let user = self.username.clone().unwrap_or_default();
+        let mut output = String::new();
+        let mut credentials_sent = 0;
+        let mut attempts = 0;
+
+        while attempts < console.wake_attempts {
+            let chunk = match self.read_until(&stop, Some(console.wake_interval)) {
+                Ok(chunk) => chunk,
+                Err(Error::Timeout { partial_output, .. }) => partial_output,
+                Err(e) => return Err(e),
+            };
+            output.push_str(&chunk);
+
+            let last_line = chunk.trim_end_matches(['\r', '\n']);
+            let answer = if password_prompt.is_match(last_line) {
+                Some(self.password.clone().unwrap_or_default())
+            } else if username_prompt.is_match(last_line) {
+                Some(user.clone())
+            } else if prompt.is_match(&chunk) {
+                return Ok(output);
+            } else {
+                None
+            };
+
+            match answer {
+                // A rejected login asks for the credentials again
+                Some(_) if credentials_sent >= 4 => {
+                    return Err(Error::AuthenticationFailed { user })
+                }
+                Some(answer) => {
+                    credentials_sent += 1;
+                    self.send(&answer)?;
+                }
+                None => {
+                    if self.eof {
+                        return Err(Error::Disconnected);
+                    }
+
+                    attempts += 1;
+                    self.send("")?;
+                }
+            }
+        }
+
+        Err(Error::Timeout {
+            command: String::new(),
+            elapsed: console.wake_interval * console.wake_attempts,
+            partial_output: output,
+        })
+    }
+
+    fn execute(&mut self, command: &str, prompt: &Regex) -> Result<String, Error> {
+        match self.config.command_timeout {
+            Some(timeout) => self.execute_with_timeout(command, prompt, timeout),
+            None => {
+                self.send(command)?;
+
+                let output = self.read(prompt)?;
+                let trimmed = prompt.replace_all(&output, "").to_string();
+
+                Ok(trimmed)
+            }
+        }
+    }
+
+    fn execute_with_timeout(
+        &mut self,
+        command: &str,
+        prompt: &Regex,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        self.send(command)?;
+
+        let output = self
+            .read_until(prompt, Some(timeout))
+            .map_err(|e| match e {
+                Error::Timeout {
+                    elapsed,
+                    partial_output,
+                    ..
+                } => Error::Timeout {
+                    command: command.to_owned(),
+                    elapsed,
+                    partial_output,
+                },
+                e => e,
+            })?;
+        let trimmed = prompt.replace_all(&output, "").to_string();
+
+        Ok(trimmed)
+    }
+
+    fn exec(&mut self, command: &str) -> Result<String, Error> {
+        Err(Error::CommandExecution(CommandError::Generic {
+            command: command.to_owned(),
+            message: "Exec channels are not available on console lines".to_string(),
+        }))
+    }
+
+    /// Console lines have no window size to negotiate, this is a no-op.
+    fn resize_pty(&mut self, _width: u32, _height: u32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        if self.eof || self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+
+        let mut buf = [0u8; 1];
+        let alive = match self.stream.peek(&mut buf) {
+            Ok(0) => false,
+            Ok(_) => true,
+            Err(e) => e.kind() == io::ErrorKind::WouldBlock,
+        };
+
+        self.stream.set_nonblocking(false).is_ok() && alive
+    }
+
+    fn can_reconnect(&self) -> bool {
+        self.config.reconnect.is_some() && !self.addrs.is_empty()
+    }
+
+    fn reconnect(&mut self) -> Result<(), Error> {
+        let policy = self.config.reconnect.unwrap_or_default();
+        let console = self.config.console.unwrap_or_default();
+        let mut last_error = Error::Disconnected;
+
+        for attempt in 1..=policy.max_attempts {
+            debug!("Reconnecting, attempt {}/{}", attempt, policy.max_attempts);
+
+            match Self::establish(&self.addrs, &self.config) {
+                Ok(stream) => {
+                    self.stream = stream;
+                    self.telnet = console.telnet.then(Telnet::new);
+                    self.eof = false;
+
+                    return Ok(());
+                }
+                Err(e) => {
+                    debug!("Reconnect failed: {}", e);
+                    last_error = e;
+
+                    if attempt < policy.max_attempts {
+                        thread::sleep(policy.delay);
+                    }
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telnet() {
+        let mut telnet = Telnet::new();
+
+        // WILL ECHO, DO TTYPE, data with an escaped IAC, split subnegotiation
+        let (data, replies) = telnet.process(&[IAC, WILL, ECHO, IAC, DO, 24, b'a', IAC]);
+        assert_eq!(data, b"a");
+        assert_eq!(replies, [IAC, DO, ECHO, IAC, WONT, 24]);
+
+        let (data, replies) = telnet.process(&[IAC, IAC, SB, 24, 1, IAC, SE, b'>']);
+        assert_eq!(data, [IAC, b'>']);
+        assert!(replies.is_empty());
+
+        assert_eq!(&*Telnet::escape(&[b'x', IAC]), &[b'x', IAC, IAC]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::io;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use regex::Regex;
+
+use super::connection::Connection;
+use crate::error::Error;
+use crate::Vendor;
+
+/// Output patterns identifying each vendor, more specific platforms first.
+const PATTERNS: [(&str, Vendor); 35] = [
+    (r"(?i)junos", Vendor::Juniper),
+    (r"AlliedWare Plus", Vendor::AlliedTelesis),
+    (r"Arista", Vendor::Arista),
+    (r"(?i)routeros|mikrotik", Vendor::Mikrotik),
+    (r"FortiGate|FortiOS", Vendor::Fortinet),
+    (r"PAN-OS|sw-version:", Vendor::PaloAlto),
+    (r"TiMOS", Vendor::Nokia),
+    (r"NX-OS|Nexus", Vendor::CiscoNxos),
+    (r"IOS XR", Vendor::CiscoIosxr),
+    (r"Adaptive Security Appliance", Vendor::CiscoAsa),
+    (r"Cisco Controller", Vendor::CiscoAireos),
+    (r"\bSG\d{3}|\bCBS\d{3}", Vendor::CiscoSb),
+    (r"ArubaOS-CX|AOS-CX", Vendor::ArubaCx),
+    (r"ArubaOS.*Instant", Vendor::ArubaInstant),
+    (r"ArubaOS", Vendor::Aruba),
+    (r"ProCurve|Image stamp:", Vendor::HpeProcurve),
+    (r"ExtremeXOS|EXOS", Vendor::Extreme),
+    (
+        r"Network Operating System Version|Brocade",
+        Vendor::BrocadeNos,
+    ),
+    (r"OS10", Vendor::DellOs10),
+    (r"ZXR10", Vendor::Zte),
+    (r"VyOS", Vendor::Vyos),
+    (r"EdgeSwitch", Vendor::EdgeSwitch),
+    (r"(?i)jetstream|tp-link", Vendor::Tplink),
+    (r"(?i)maipu|mypower", Vendor::Maipu),
+    (r"(?i)dptech", Vendor::Dptech),
+    (r"(?i)fiberhome", Vendor::Fiberhome),
+    (r"StoneOS|Hillstone", Vendor::Hillstone),
+    (r"FastIron|ICX\d", Vendor::RuckusIcx),
+    (r"SecPath", Vendor::H3cSecpath),
+    (r"H3C|Comware", Vendor::H3C),
+    (r"USG\d", Vendor::HuaweiUsg),
+    (r"AC6\d{3}|AirEngine ?9\d{3}", Vendor::HuaweiAc),
+    (r"(?i)huawei", Vendor::Huawei),
+    (r"Ruijie", Vendor::Ruijie),
+    (r"Cisco IOS", Vendor::Cisco),
+];
+
+/// The fingerprint patterns, compiled on first use.
+static FINGERPRINTS: LazyLock<Vec<(Regex, Vendor)>> = LazyLock::new(|| {
+    PATTERNS
+        .iter()
+        .map(|&(pattern, vendor)| {
+            (
+                Regex::new(pattern).expect("Invalid fingerprint regex"),
+                vendor,
+            )
+        })
+        .collect()
+});
+
+/// Time given to each probe command, paginated outputs never reach the prompt.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn fingerprint(output: &str) -> Option<Vendor> {
+    FINGERPRINTS
+        .iter()
+        .find(|(regex, _)| regex.is_match(output))
+        .map(|(_, vendor)| *vendor)
+}
+
+/// Identifies the vendor from the login banner and prompt, then from the output of
+/// `display version` or `show version`, whichever the prompt style suggests first.
+pub(crate) fn detect<C: Connection>(connection: &mut C) -> Result<Vendor, Error> {
+    let prompt = Regex::new(r"[>#\]$] ?$").expect("Invalid prompt regex");
+    let banner = connection.login(&prompt)?;
+
+    if let Some(vendor) = fingerprint(&banner) {
+        return Ok(vendor);
+    }
+
+    let last_line = banner.trim_end().lines().last().unwrap_or_default().trim();
+    let commands = if last_line.starts_with('<') || last_line.starts_with('[') {
+        ["display version", "show version"]
+    } else {
+        ["show version", "display version"]
+    };
+
+    for command in commands {
+        let output = match connection.execute_with_timeout(command, &prompt, PROBE_TIMEOUT) {
+            Ok(output) => output,
+            Err(Error::Timeout { partial_output, .. }) => partial_output,
+            Err(e) => return Err(e),
+        };
+
+        if let Some(vendor) = fingerprint(&output) {
+            return Ok(vendor);
+        }
+    }
+
+    // A plain shell answers neither command
+    if last_line.ends_with('$') || last_line.ends_with('#') {
+        let output = connection.execute_with_timeout("uname -s", &prompt, PROBE_TIMEOUT)?;
+
+        if output.contains("Linux") {
+            return Ok(Vendor::Linux);
+        }
+    }
+
+    Err(Error::Generic(io::Error::other(
+        "Unable to detect the device vendor",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(
+            fingerprint("H3C Comware Software, Version 7.1.070, Release 6615"),
+            Some(Vendor::H3C)
+        );
+        assert_eq!(
+            fingerprint("Huawei Versatile Routing Platform Software\r\nUSG6555E V600R007"),
+            Some(Vendor::HuaweiUsg)
+        );
+        assert_eq!(
+            fingerprint("Cisco Nexus Operating System (NX-OS) Software"),
+            Some(Vendor::CiscoNxos)
+        );
+        assert_eq!(
+            fingerprint("Cisco IOS Software, C2960X Software"),
+            Some(Vendor::Cisco)
+        );
+        assert_eq!(fingerprint("% Unknown command"), None);
+    }
+}
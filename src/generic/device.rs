@@ -1,7 +1,116 @@
+use std::collections::HashMap;
 use std::net::ToSocketAddrs;
+use std::time::Duration;
 
 use super::config::ConfigSession;
-use crate::{error::Error, ConnectConfig};
+use super::parse;
+use super::types::{
+    AccessPoint, Acl, ArpEntry, BgpPeer, CpuMemory, DateTime, DhcpBinding, Filesystem, Hop,
+    Interface, InterfaceCounters, InterfaceDetail, Inventory, License, LldpNeighbor, LocalUser,
+    MacEntry, NtpStatus, OspfNeighbor, PoePort, PortChannel, Radio, RouteEntry, Sensor,
+    StackMember, StpInstance, Transceiver, Vlan, WirelessClient,
+};
+use crate::error::{CommandError, Error};
+use crate::parsing::index::TemplateIndex;
+use crate::ConnectConfig;
+
+/// Optional ping parameters, unset fields keep the device defaults.
+#[derive(Debug, Clone, Default)]
+pub struct PingOptions {
+    /// Number of echo requests sent.
+    pub count: Option<u32>,
+    /// Payload size in bytes.
+    pub size: Option<u32>,
+    /// Source interface or address of the requests.
+    pub source: Option<String>,
+    pub vrf: Option<String>,
+    /// Time waited for each reply.
+    pub timeout: Option<Duration>,
+}
+
+impl PingOptions {
+    fn is_default(&self) -> bool {
+        self.count.is_none()
+            && self.size.is_none()
+            && self.source.is_none()
+            && self.vrf.is_none()
+            && self.timeout.is_none()
+    }
+}
+
+/// Optional traceroute parameters, unset fields keep the device defaults.
+#[derive(Debug, Clone, Default)]
+pub struct TracerouteOptions {
+    pub max_hops: Option<u8>,
+    /// Source interface or address of the probes.
+    pub source: Option<String>,
+    pub vrf: Option<String>,
+    /// Time waited for each probe reply.
+    pub timeout: Option<Duration>,
+    /// Skips the reverse DNS lookup of the hops.
+    pub numeric: bool,
+}
+
+impl TracerouteOptions {
+    fn is_default(&self) -> bool {
+        self.max_hops.is_none()
+            && self.source.is_none()
+            && self.vrf.is_none()
+            && self.timeout.is_none()
+            && !self.numeric
+    }
+}
+
+/// Commands of the configuration methods, for their default implementations through
+/// `NetworkDevice::config_commands`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfigCommands {
+    /// Command saving the running configuration as the startup one.
+    pub save: Option<&'static str>,
+    /// Command showing the full running configuration, read with `CONFIG_TIMEOUT`.
+    pub running: Option<&'static str>,
+    /// Command showing the saved configuration the device boots with.
+    pub startup: Option<&'static str>,
+    /// Applied changes persist on their own, e.g. once committed, so saving does nothing
+    /// and the running configuration is the startup one.
+    pub persistent: bool,
+}
+
+/// SNMP v2c community.
+#[derive(Debug, Clone, Default)]
+pub struct SnmpCommunity {
+    pub name: String,
+    pub read_write: bool,
+}
+
+/// SNMP v3 user, authenticating with SHA and encrypting with AES-128.
+#[derive(Debug, Clone, Default)]
+pub struct SnmpUser {
+    pub name: String,
+    /// Group created for the user with its security level.
+    pub group: String,
+    pub auth_password: String,
+    /// Privacy password, the user only authenticates without it.
+    pub privacy_password: Option<String>,
+}
+
+/// SNMP agent settings for `configure_snmp`, empty fields are left as configured.
+#[derive(Debug, Clone, Default)]
+pub struct SnmpConfig {
+    pub communities: Vec<SnmpCommunity>,
+    pub users: Vec<SnmpUser>,
+    pub location: Option<String>,
+    pub contact: Option<String>,
+}
+
+/// Banner shown by the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerKind {
+    /// Message of the day, shown once logged in on VRP.
+    Motd,
+    /// Shown before the login.
+    Login,
+}
 
 /// Trait for network devices with vendor-specific behavior.
 pub trait NetworkDevice {
@@ -32,10 +141,35 @@ pub trait NetworkDevice {
     /// Used for both general commands and commands in configuration mode.
     fn execute(&mut self, command: &str) -> Result<String, Error>;
 
+    /// Executes a command, overriding the configured command timeout.
+    fn execute_with_timeout(&mut self, command: &str, timeout: Duration) -> Result<String, Error>;
+
+    /// Executes a command and parses the output with the template the index maps the
+    /// platform and command to, e.g. `Vendor::ntc_platform()` for the built-in drivers.
+    fn execute_parsed(
+        &mut self,
+        index: &TemplateIndex,
+        platform: &str,
+        command: &str,
+    ) -> Result<Vec<HashMap<String, String>>, Error> {
+        if index.templates(platform, command).is_none() {
+            return Err(Error::NoTemplate {
+                platform: platform.to_string(),
+                command: command.to_string(),
+            });
+        }
+
+        let output = self.execute(command)?;
+        index.parse(platform, command, &output)
+    }
+
     fn enter_config(&mut self) -> Result<Box<dyn ConfigSession + '_>, Error>;
 
     fn exit(&mut self) -> Result<(), Error>;
 
+    /// Checks whether the underlying connection is still usable, so pools can evict dead sessions.
+    fn is_alive(&mut self) -> bool;
+
     /// Retrieves the device version information.
     fn version(&mut self) -> Result<String, Error>;
 
@@ -45,6 +179,448 @@ pub trait NetworkDevice {
     /// Performs a ping operation to the specified IP.
     fn ping(&mut self, ip: &str) -> Result<String, Error>;
 
+    /// Performs a ping operation with count, size, source, VRF or timeout.
+    ///
+    /// Drivers without support for the options only accept the defaults.
+    fn ping_with(&mut self, ip: &str, options: &PingOptions) -> Result<String, Error> {
+        if !options.is_default() {
+            return Err(unsupported("ping options"));
+        }
+
+        self.ping(ip)
+    }
+
     /// Performs a traceroute operation to the specified IP.
     fn traceroute(&mut self, ip: &str) -> Result<String, Error>;
+
+    /// Performs a traceroute with hop limit, source, VRF, probe timeout or numeric output,
+    /// and parses the hops of the output.
+    ///
+    /// Drivers without support for the options only accept the defaults.
+    fn traceroute_with(
+        &mut self,
+        ip: &str,
+        options: &TracerouteOptions,
+    ) -> Result<Vec<Hop>, Error> {
+        if !options.is_default() {
+            return Err(unsupported("traceroute options"));
+        }
+
+        let output = self.traceroute(ip)?;
+
+        Ok(parse::hops(&output))
+    }
+
+    /// Lists the interfaces with their status, speed, duplex, description and addresses.
+    fn interfaces(&mut self) -> Result<Vec<Interface>, Error> {
+        Err(unsupported("interfaces"))
+    }
+
+    /// Returns the state, counters and rates of a single interface.
+    fn interface_detail(&mut self, _name: &str) -> Result<InterfaceDetail, Error> {
+        Err(unsupported("interface detail"))
+    }
+
+    /// Returns the ARP cache.
+    fn arp_table(&mut self) -> Result<Vec<ArpEntry>, Error> {
+        Err(unsupported("ARP table"))
+    }
+
+    /// Returns the MAC address table.
+    fn mac_table(&mut self) -> Result<Vec<MacEntry>, Error> {
+        Err(unsupported("MAC address table"))
+    }
+
+    /// Returns the neighbors discovered through LLDP.
+    fn lldp_neighbors(&mut self) -> Result<Vec<LldpNeighbor>, Error> {
+        Err(unsupported("LLDP neighbors"))
+    }
+
+    /// Returns the neighbors discovered through CDP, with an empty chassis ID.
+    fn cdp_neighbors(&mut self) -> Result<Vec<LldpNeighbor>, Error> {
+        Err(unsupported("CDP neighbors"))
+    }
+
+    /// Returns the IPv4 routing table of a VRF, or of the global table.
+    fn routes(&mut self, _vrf: Option<&str>) -> Result<Vec<RouteEntry>, Error> {
+        Err(unsupported("routing table"))
+    }
+
+    /// Returns the IPv4 unicast BGP peers with their session state.
+    fn bgp_summary(&mut self) -> Result<Vec<BgpPeer>, Error> {
+        Err(unsupported("BGP summary"))
+    }
+
+    /// Returns the OSPF adjacencies of all processes.
+    fn ospf_neighbors(&mut self) -> Result<Vec<OspfNeighbor>, Error> {
+        Err(unsupported("OSPF neighbors"))
+    }
+
+    /// Returns the VLANs with their tagged and untagged member ports.
+    fn vlans(&mut self) -> Result<Vec<Vlan>, Error> {
+        Err(unsupported("VLANs"))
+    }
+
+    /// Returns the chassis model and serial number with the modules, power supplies and fans.
+    fn inventory(&mut self) -> Result<Inventory, Error> {
+        Err(unsupported("inventory"))
+    }
+
+    /// Returns the temperature, fan and power supply readings.
+    fn environment(&mut self) -> Result<Vec<Sensor>, Error> {
+        Err(unsupported("environment"))
+    }
+
+    /// Returns the CPU load averages and the memory usage.
+    fn cpu_memory(&mut self) -> Result<CpuMemory, Error> {
+        Err(unsupported("CPU and memory usage"))
+    }
+
+    /// Returns the time since the last reboot, parsed from the version information.
+    fn uptime(&mut self) -> Result<Duration, Error> {
+        let output = self.version()?;
+
+        parse::uptime(&output).ok_or_else(|| unsupported("uptime"))
+    }
+
+    /// Returns the configured hostname, as of the login prompt where the driver caches it.
+    fn hostname(&mut self) -> Result<String, Error> {
+        Err(unsupported("hostname"))
+    }
+
+    /// Returns the NTP synchronization state with the reference clock and offset.
+    fn ntp_status(&mut self) -> Result<NtpStatus, Error> {
+        Err(unsupported("NTP status"))
+    }
+
+    /// Returns the optic type, power levels, temperature and alarms of the transceivers.
+    fn transceivers(&mut self) -> Result<Vec<Transceiver>, Error> {
+        Err(unsupported("transceivers"))
+    }
+
+    /// Returns the PoE state, class and power draw of the ports.
+    fn poe_status(&mut self) -> Result<Vec<PoePort>, Error> {
+        Err(unsupported("PoE status"))
+    }
+
+    /// Returns the packet, byte, error and discard counters of all interfaces, see
+    /// `counter_rates` for the rates between two snapshots.
+    fn interface_counters(&mut self) -> Result<Vec<InterfaceCounters>, Error> {
+        Err(unsupported("interface counters"))
+    }
+
+    /// Returns the spanning tree instances with their root bridge and port states.
+    fn spanning_tree(&mut self) -> Result<Vec<StpInstance>, Error> {
+        Err(unsupported("spanning tree"))
+    }
+
+    /// Returns the link aggregations with their protocol and member port states.
+    fn port_channels(&mut self) -> Result<Vec<PortChannel>, Error> {
+        Err(unsupported("port channels"))
+    }
+
+    /// Returns the members of the switch stack with their role, priority and model.
+    fn stack_members(&mut self) -> Result<Vec<StackMember>, Error> {
+        Err(unsupported("stack members"))
+    }
+
+    /// Returns the DHCP snooping bindings.
+    fn dhcp_snooping_bindings(&mut self) -> Result<Vec<DhcpBinding>, Error> {
+        Err(unsupported("DHCP snooping bindings"))
+    }
+
+    /// Returns the access lists with their rules and hit counts.
+    fn acls(&mut self) -> Result<Vec<Acl>, Error> {
+        Err(unsupported("ACLs"))
+    }
+
+    /// Returns the local user accounts with their privilege level and services.
+    fn local_users(&mut self) -> Result<Vec<LocalUser>, Error> {
+        Err(unsupported("local users"))
+    }
+
+    /// Returns the installed licenses with their states and expiry.
+    fn licenses(&mut self) -> Result<Vec<License>, Error> {
+        Err(unsupported("licenses"))
+    }
+
+    /// Returns the access points managed by a wireless controller.
+    fn aps(&mut self) -> Result<Vec<AccessPoint>, Error> {
+        Err(unsupported("access points"))
+    }
+
+    /// Returns the wireless clients associated to the access points of a controller.
+    fn wireless_clients(&mut self) -> Result<Vec<WirelessClient>, Error> {
+        Err(unsupported("wireless clients"))
+    }
+
+    /// Returns the channel, power, utilization and noise floor of every AP radio.
+    fn radios(&mut self) -> Result<Vec<Radio>, Error> {
+        Err(unsupported("radios"))
+    }
+
+    /// Returns the files and the total and free space of the default filesystem.
+    fn filesystem(&mut self) -> Result<Filesystem, Error> {
+        Err(unsupported("filesystem"))
+    }
+
+    /// Returns the local date and time of the device clock.
+    fn clock(&mut self) -> Result<DateTime, Error> {
+        Err(unsupported("clock"))
+    }
+
+    /// Sets the device clock to a local date and time.
+    fn set_clock(&mut self, _time: &DateTime) -> Result<(), Error> {
+        Err(unsupported("setting the clock"))
+    }
+
+    /// Reboots the device, answering its confirmation questions and saving the
+    /// configuration first if asked, then reconnects within `wait` if given.
+    ///
+    /// Returns the downtime until the device was reachable again when waiting.
+    fn reboot(&mut self, _save: bool, _wait: Option<Duration>) -> Result<Option<Duration>, Error> {
+        Err(unsupported("reboot"))
+    }
+
+    /// Commands of the configuration methods, which run them by default.
+    fn config_commands(&self) -> ConfigCommands {
+        ConfigCommands::default()
+    }
+
+    /// Saves the running configuration as the startup configuration.
+    fn save_config(&mut self) -> Result<(), Error> {
+        let commands = self.config_commands();
+
+        match commands.save {
+            Some(command) => self.execute_with_timeout(command, CONFIG_TIMEOUT).map(drop),
+            None if commands.persistent => Ok(()),
+            None => Err(unsupported("saving the configuration")),
+        }
+    }
+
+    /// Returns the full running configuration.
+    fn running_config(&mut self) -> Result<String, Error> {
+        match self.config_commands().running {
+            Some(command) => self.execute_with_timeout(command, CONFIG_TIMEOUT),
+            None => Err(unsupported("running configuration")),
+        }
+    }
+
+    /// Returns the saved configuration the device boots with.
+    fn startup_config(&mut self) -> Result<String, Error> {
+        let commands = self.config_commands();
+
+        match commands.startup {
+            Some(command) => self.execute_with_timeout(command, CONFIG_TIMEOUT),
+            None if commands.persistent => self.running_config(),
+            None => Err(unsupported("startup configuration")),
+        }
+    }
+
+    /// Checks whether the running configuration differs from the startup one, i.e.
+    /// whether a save is pending.
+    fn has_unsaved_changes(&mut self) -> Result<bool, Error> {
+        let running = self.running_config()?;
+        let startup = self.startup_config()?;
+
+        Ok(parse::configs_differ(&running, &startup))
+    }
+
+    /// Shuts an interface down or brings it up from the configuration mode, returning
+    /// its state afterwards. A shut interface must also be operationally down.
+    fn set_interface_state(&mut self, _name: &str, _up: bool) -> Result<InterfaceDetail, Error> {
+        Err(unsupported("setting the interface state"))
+    }
+
+    /// Sets the description of an interface, removing it when empty, and returns the
+    /// description applied after the limits of the platform.
+    fn set_interface_description(
+        &mut self,
+        _name: &str,
+        _description: &str,
+    ) -> Result<String, Error> {
+        Err(unsupported("setting the interface description"))
+    }
+
+    /// Creates a VLAN, or renames it if it exists; an empty name keeps the default.
+    fn create_vlan(&mut self, _id: u16, _name: &str) -> Result<(), Error> {
+        Err(unsupported("creating VLANs"))
+    }
+
+    /// Deletes a VLAN, refusing VLAN 1 and, unless forced, VLANs with member ports.
+    fn delete_vlan(&mut self, _id: u16, _force: bool) -> Result<(), Error> {
+        Err(unsupported("deleting VLANs"))
+    }
+
+    /// Enables the SNMP agent with v2c communities, v3 users and system information.
+    fn configure_snmp(&mut self, _config: &SnmpConfig) -> Result<(), Error> {
+        Err(unsupported("SNMP configuration"))
+    }
+
+    /// Sets a banner of one or more lines, removing it when the text is empty.
+    fn set_banner(&mut self, _kind: BannerKind, _text: &str) -> Result<(), Error> {
+        Err(unsupported("banners"))
+    }
+}
+
+/// Time allowed for reading or saving a full configuration on big devices.
+pub(crate) const CONFIG_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Error of a typed query whose command output could not be parsed.
+pub(crate) fn unexpected_output(command: &str) -> Error {
+    Error::CommandExecution(CommandError::UnexpectedOutput {
+        command: command.to_string(),
+    })
+}
+
+/// Error of the trait methods a driver does not implement.
+pub(crate) fn unsupported(operation: &str) -> Error {
+    Error::Unsupported {
+        operation: operation.to_string(),
+    }
+}
+
+/// Error of an interface whose state differs from the requested one after changing it.
+pub(crate) fn check_interface_state(detail: &InterfaceDetail, up: bool) -> Result<(), Error> {
+    if detail.admin_up == up && (up || !detail.oper_up) {
+        return Ok(());
+    }
+
+    Err(Error::CommandExecution(CommandError::Generic {
+        command: format!("interface {}", detail.name),
+        message: format!(
+            "Interface is administratively {} and operationally {}",
+            if detail.admin_up { "up" } else { "down" },
+            if detail.oper_up { "up" } else { "down" }
+        ),
+    }))
+}
+
+/// Error of a VLAN ID outside 1-4094.
+pub(crate) fn check_vlan_id(id: u16) -> Result<(), Error> {
+    if (1..=4094).contains(&id) {
+        return Ok(());
+    }
+
+    Err(Error::CommandExecution(CommandError::Generic {
+        command: format!("vlan {}", id),
+        message: "VLAN ID must be between 1 and 4094".to_string(),
+    }))
+}
+
+/// Error of a VLAN deletion refused by the safety checks: VLAN 1 cannot be deleted,
+/// and a VLAN with member ports only when forced.
+pub(crate) fn check_vlan_deletable(vlans: &[Vlan], id: u16, force: bool) -> Result<(), Error> {
+    check_vlan_id(id)?;
+
+    let message = match vlans.iter().find(|vlan| vlan.id == id) {
+        _ if id == 1 => "VLAN 1 cannot be deleted".to_string(),
+        Some(vlan) if !force && !vlan.ports.is_empty() => {
+            let ports: Vec<&str> = vlan.ports.iter().map(|port| port.name.as_str()).collect();
+            format!("VLAN has member ports: {}", ports.join(", "))
+        }
+        _ => return Ok(()),
+    };
+
+    Err(Error::CommandExecution(CommandError::Generic {
+        command: format!("vlan {}", id),
+        message,
+    }))
+}
+
+/// Fits a description to the CLI of a platform: control characters become spaces,
+/// `?` which would open the inline help is dropped, and the text is cut to `max_len`
+/// characters.
+pub(crate) fn description_text(description: &str, max_len: usize) -> String {
+    description
+        .chars()
+        .filter(|&c| c != '?')
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .chars()
+        .take(max_len)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Wraps banner text in a delimiter character it does not contain, as the CLIs read
+/// banners of several lines up to the next delimiter. Line endings become `\n` and
+/// other control characters are dropped.
+pub(crate) fn delimited_text(text: &str) -> Result<String, Error> {
+    let text: String = text
+        .replace("\r\n", "\n")
+        .chars()
+        .filter(|&c| c == '\n' || !c.is_control())
+        .collect();
+    let text = text.trim_end_matches('\n');
+
+    let delimiter = ['^', '%', '#', '~', '@', '|', '$']
+        .into_iter()
+        .find(|&delimiter| !text.contains(delimiter))
+        .ok_or_else(|| {
+            Error::CommandExecution(CommandError::Generic {
+                command: String::from("banner"),
+                message: "No delimiter left that the banner text does not contain".to_string(),
+            })
+        })?;
+
+    Ok(format!("{}{}{}", delimiter, text, delimiter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::types::VlanPort;
+
+    #[test]
+    fn test_description_text() {
+        assert_eq!(
+            description_text(" uplink to core?\r\n", 80),
+            "uplink to core"
+        );
+        assert_eq!(description_text("to\tSW2 Gi0/1", 80), "to SW2 Gi0/1");
+        assert_eq!(description_text("abcdef ghi", 7), "abcdef");
+        assert_eq!(description_text("机房 A", 80), "机房 A");
+    }
+
+    #[test]
+    fn test_check_vlan_deletable() {
+        let vlans = [
+            Vlan {
+                id: 10,
+                name: "users".to_string(),
+                ports: vec![VlanPort {
+                    name: "GE1/0/1".to_string(),
+                    tagged: false,
+                }],
+            },
+            Vlan {
+                id: 20,
+                ..Default::default()
+            },
+        ];
+
+        assert!(check_vlan_deletable(&vlans, 10, false).is_err());
+        assert!(check_vlan_deletable(&vlans, 10, true).is_ok());
+        assert!(check_vlan_deletable(&vlans, 20, false).is_ok());
+        assert!(check_vlan_deletable(&vlans, 1, true).is_err());
+        assert!(check_vlan_deletable(&vlans, 4095, true).is_err());
+    }
+
+    #[test]
+    fn test_delimited_text() {
+        let text =
+            delimited_text("Authorized access only\r\nViolators will be prosecuted\r\n").unwrap();
+        assert_eq!(
+            text,
+            "^Authorized access only\nViolators will be prosecuted^"
+        );
+
+        let text = delimited_text("Call ^1234 or %5678").unwrap();
+        assert_eq!(text, "#Call ^1234 or %5678#");
+
+        assert!(delimited_text("^%#~@|$").is_err());
+    }
 }
@@ -0,0 +1,90 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use super::connection::Connection;
+use crate::error::Error;
+
+/// Endings of the confirmation questions asked by commands like `reboot` or `save`,
+/// trimmed from the output like a prompt.
+const QUESTION: &str = r"(?i)(?:\[Y/N\]|\[yes/no\]|\[confirm\]|press the enter key\)):?\s*$";
+
+/// Questions answered for one command at most, in case an answer is not accepted.
+const MAX_QUESTIONS: usize = 8;
+
+/// Time between the reconnect attempts while a device reboots.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Executes a command that asks confirmation questions until the prompt returns,
+/// answering each question with the answer of the first key the question contains,
+/// compared case-insensitively.
+///
+/// The answered output is returned as is, the caller checks it for error messages.
+pub(crate) fn dialog<C: Connection>(
+    connection: &mut C,
+    prompt: &Regex,
+    command: &str,
+    answers: &[(&str, &str)],
+) -> Result<String, Error> {
+    let expect = Regex::new(&format!("(?:{})|(?:{})", prompt.as_str(), QUESTION))
+        .expect("Invalid dialog regex");
+    let mut output = connection.execute(command, &expect)?;
+
+    for _ in 0..MAX_QUESTIONS {
+        let Some(answer) = answer(&output, answers) else {
+            break;
+        };
+
+        output.push_str(&connection.execute(answer, &expect)?);
+    }
+
+    Ok(output)
+}
+
+/// Returns the answer to the question on the last line of the output, if any.
+fn answer<'a>(output: &str, answers: &[(&str, &'a str)]) -> Option<&'a str> {
+    let question = output.lines().last()?.to_lowercase();
+
+    answers
+        .iter()
+        .find(|(key, _)| question.contains(&key.to_lowercase()))
+        .map(|&(_, answer)| answer)
+}
+
+/// Retries reconnecting to a rebooting device until it succeeds or the timeout
+/// expires, returning the time it took.
+pub(crate) fn await_return(
+    timeout: Duration,
+    mut reconnect: impl FnMut() -> Result<(), Error>,
+) -> Result<Duration, Error> {
+    let start = Instant::now();
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        match reconnect() {
+            Ok(()) => return Ok(start.elapsed()),
+            Err(e) if start.elapsed() >= timeout => return Err(e),
+            Err(e) => log::debug!("Device not back yet after {:?}: {}", start.elapsed(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer() {
+        let answers = [("save current configuration", "N"), ("continue", "Y")];
+
+        let output = "reboot\r\n Current configuration may be lost after the reboot, save current configuration? ";
+        assert_eq!(answer(output, &answers), Some("N"));
+
+        let output = "N\r\nThis command will reboot the device. Continue? ";
+        assert_eq!(answer(output, &answers), Some("Y"));
+
+        assert_eq!(answer("Info: Operation succeeded.\r\n", &answers), None);
+    }
+}
@@ -1,3 +1,12 @@
+pub(crate) mod cli;
 pub mod config;
 pub mod connection;
+pub mod console;
+pub mod detect;
 pub mod device;
+pub(crate) mod dialog;
+pub(crate) mod parse;
+pub mod registry;
+pub mod socket;
+pub mod ssh_config;
+pub mod types;
@@ -0,0 +1,890 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use regex::Regex;
+
+use super::types::{
+    Acl, AclAction, AclRule, ComponentKind, Duplex, FileEntry, Filesystem, Hop, InterfaceCounters,
+    InterfaceDetail, Inventory, InventoryItem, LldpNeighbor, RouteEntry, StpInstance, StpPort,
+};
+
+/// Returns the byte offset of a column in a table header.
+pub(crate) fn column(header: &str, name: &str) -> Option<usize> {
+    header.find(name)
+}
+
+/// Returns the trimmed text of a line from a column offset, e.g. a free-form
+/// description in the last column.
+pub(crate) fn from_column(line: &str, offset: usize) -> &str {
+    line.get(offset..).unwrap_or_default().trim()
+}
+
+/// Returns the first group of a pattern in the output, parsed.
+pub(crate) fn capture<T: FromStr>(output: &str, pattern: &str) -> Option<T> {
+    Regex::new(pattern)
+        .expect("Invalid capture regex")
+        .captures(output)?
+        .get(1)?
+        .as_str()
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Returns the trimmed value of a `Key : value` line.
+pub(crate) fn field(output: &str, key: &str) -> Option<String> {
+    let pattern = format!(r"(?m)^\s*{}\s*:(.*)$", regex::escape(key));
+
+    capture::<String>(output, &pattern).filter(|value| !value.is_empty())
+}
+
+/// Builds a neighbor from the `Key : value` block of one neighbor in the verbose
+/// LLDP listings of VRP and Comware.
+pub(crate) fn lldp_neighbor(local_port: &str, block: &str) -> LldpNeighbor {
+    LldpNeighbor {
+        local_port: local_port.to_string(),
+        system_name: field(block, "System name").unwrap_or_default(),
+        chassis_id: field(block, "Chassis ID").unwrap_or_default(),
+        port_id: field(block, "Port ID").unwrap_or_default(),
+        port_description: field(block, "Port description").unwrap_or_default(),
+        capabilities: field(block, "System capabilities enabled")
+            .unwrap_or_default()
+            .split([',', ' '])
+            .filter(|capability| !capability.is_empty())
+            .map(str::to_lowercase)
+            .collect(),
+        management_ip: field(block, "Management address"),
+    }
+}
+
+/// Parses a speed such as `1000`, `a-1000`, `1G(a)` or `100M` into Mbit/s.
+///
+/// Plain numbers are taken as Mbit/s, `auto` and unknown values give `None`.
+pub(crate) fn speed_mbps(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let text = text.strip_prefix("a-").unwrap_or(text);
+    let text = text.strip_suffix("(a)").unwrap_or(text);
+
+    let digits = text.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let value: u64 = digits.parse().ok()?;
+
+    match text[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "M" | "MB" | "MBPS" => Some(value),
+        "G" | "GB" | "GBPS" => Some(value * 1000),
+        "K" | "KB" | "KBPS" => Some(value / 1000),
+        _ => None,
+    }
+}
+
+/// Parses a duplex mode such as `a-full`, `half`, `F(a)` or `A`.
+pub(crate) fn duplex(text: &str) -> Option<Duplex> {
+    let text = text.trim();
+    let text = text.strip_prefix("a-").unwrap_or(text);
+    let text = text.strip_suffix("(a)").unwrap_or(text);
+
+    match text.to_ascii_lowercase().as_str() {
+        "full" | "f" => Some(Duplex::Full),
+        "half" | "h" => Some(Duplex::Half),
+        "auto" | "a" => Some(Duplex::Auto),
+        _ => None,
+    }
+}
+
+/// Normalizes a MAC address in dotted(`0011.2233.4455`), dashed(`0011-2233-4455`)
+/// or colon notation to lowercase colon separated octets.
+pub(crate) fn mac(text: &str) -> String {
+    let digits: Vec<char> = text
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    if digits.len() != 12 {
+        return text.to_lowercase();
+    }
+
+    digits
+        .chunks(2)
+        .map(|octet| octet.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Checks for a MAC address in dotted, dashed or colon notation.
+pub(crate) fn is_mac(text: &str) -> bool {
+    text.chars().filter(|c| c.is_ascii_hexdigit()).count() == 12
+        && text
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || ".:-".contains(c))
+}
+
+/// Parses `display ip routing-table` of VRP and Comware:
+/// `Destination/Mask Proto Pre Cost [Flags] NextHop Interface` rows, additional next
+/// hops of a route continuing on rows without destination.
+pub(crate) fn routing_table(output: &str) -> Vec<RouteEntry> {
+    let mut routes = Vec::new();
+    let mut prefix: Option<String> = None;
+
+    for line in output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("Destination/Mask"))
+        .skip(1)
+    {
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.first().is_some_and(|field| field.contains('/')) {
+            prefix = Some(fields.remove(0).to_string());
+        }
+
+        let Some(prefix) = prefix.clone().filter(|_| fields.len() >= 5) else {
+            continue;
+        };
+
+        let protocol = match fields[0].to_lowercase().as_str() {
+            "direct" => "connected".to_string(),
+            protocol => protocol.to_string(),
+        };
+
+        routes.push(RouteEntry {
+            prefix,
+            next_hop: Some(fields[fields.len() - 2].to_string()),
+            protocol,
+            preference: fields[1].parse().ok(),
+            metric: fields[2].parse().ok(),
+            interface: Some(fields[fields.len() - 1].to_string()),
+        });
+    }
+
+    routes
+}
+
+/// Parses a duration such as `00:00:33`, `1:02:03` or plain seconds.
+pub(crate) fn duration(text: &str) -> Option<Duration> {
+    text.trim()
+        .split(':')
+        .try_fold(0, |total: u64, part| {
+            Some(total * 60 + part.parse::<u64>().ok()?)
+        })
+        .map(Duration::from_secs)
+}
+
+/// Expands a VLAN list such as `1,10-12,20` into its IDs.
+pub(crate) fn vlan_list(text: &str) -> Vec<u16> {
+    text.split(',')
+        .filter_map(|part| match part.trim().split_once('-') {
+            Some((start, end)) => Some((start.parse().ok()?..=end.parse().ok()?).collect()),
+            None => part.trim().parse().ok().map(|id| vec![id]),
+        })
+        .flatten()
+        .collect()
+}
+
+/// Guesses the kind of a hardware component from its name or description.
+pub(crate) fn component_kind(text: &str) -> ComponentKind {
+    let text = text.to_lowercase();
+
+    if text.contains("power") || text.contains("pwr") || text.contains("psu") {
+        ComponentKind::PowerSupply
+    } else if text.contains("fan") {
+        ComponentKind::Fan
+    } else if text.contains("chassis") {
+        ComponentKind::Chassis
+    } else {
+        ComponentKind::Module
+    }
+}
+
+/// Builds the inventory from the components, taking model and serial number from the
+/// chassis or, for fixed and stacked switches, from the first component.
+pub(crate) fn inventory(items: Vec<InventoryItem>) -> Option<Inventory> {
+    let chassis = items
+        .iter()
+        .find(|item| item.kind == ComponentKind::Chassis)
+        .or(items.first())?;
+
+    Some(Inventory {
+        model: chassis.model.clone(),
+        serial: chassis.serial.clone(),
+        items,
+    })
+}
+
+/// Parses the uptime of a version listing, either counted in units such as
+/// `uptime is 3 weeks, 2 days, 4 hours, 5 minutes` or as `uptime : 5:02:03:04`
+/// (days, hours, minutes and seconds).
+pub(crate) fn uptime(output: &str) -> Option<Duration> {
+    let text = capture::<String>(output, r"(?im)uptime(?:\s+is|\s*:)\s*(.+)$")?.to_lowercase();
+
+    let parts: Option<Vec<u64>> = text.split(':').map(|part| part.parse().ok()).collect();
+    if let Some([days, hours, minutes, seconds]) = parts.as_deref() {
+        return Some(Duration::from_secs(
+            ((days * 24 + hours) * 60 + minutes) * 60 + seconds,
+        ));
+    }
+
+    let unit =
+        Regex::new(r"(\d+)\s*(year|week|day|hour|minute|second)").expect("Invalid unit regex");
+    let mut units = unit.captures_iter(&text).peekable();
+    units.peek()?;
+
+    let seconds = units
+        .map(|caps| {
+            let value: u64 = caps[1].parse().unwrap_or_default();
+
+            value
+                * match &caps[2] {
+                    "year" => 365 * 86400,
+                    "week" => 7 * 86400,
+                    "day" => 86400,
+                    "hour" => 3600,
+                    "minute" => 60,
+                    _ => 1,
+                }
+        })
+        .sum();
+
+    Some(Duration::from_secs(seconds))
+}
+
+/// Extracts the hostname from the prompt ending an output, e.g. `core-sw1#`,
+/// `core-sw1(config)#`, `<HUAWEI>` or `[~CE6850]`.
+pub(crate) fn prompt_hostname(output: &str) -> Option<String> {
+    let prompt = output
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())?;
+    let name = prompt
+        .trim_start_matches(['<', '[', '~', '*'])
+        .trim_end_matches(['>', ']', '#'])
+        .split('(')
+        .next()?;
+
+    (!name.is_empty() && !name.contains(char::is_whitespace)).then(|| name.to_string())
+}
+
+/// Parses the `Transceiver Type` of each `<port> transceiver information:` block of
+/// `display transceiver` in VRP and Comware.
+pub(crate) fn transceiver_types(output: &str) -> HashMap<String, String> {
+    let mut types = HashMap::new();
+    let mut port = None;
+
+    for line in output.lines() {
+        if line.trim_end().ends_with("transceiver information:") {
+            port = line.split_whitespace().next();
+        } else if let (Some(port), Some((key, value))) = (port, line.split_once(':')) {
+            if key.trim() == "Transceiver Type" {
+                types.insert(port.to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    types
+}
+
+/// Describes a reading below its low or above its high alarm threshold, e.g.
+/// `rx power low alarm`.
+pub(crate) fn threshold_alarm(
+    name: &str,
+    value: Option<f64>,
+    low: Option<f64>,
+    high: Option<f64>,
+) -> Option<String> {
+    let value = value?;
+
+    if low.is_some_and(|low| value < low) {
+        Some(format!("{} low alarm", name))
+    } else if high.is_some_and(|high| value > high) {
+        Some(format!("{} high alarm", name))
+    } else {
+        None
+    }
+}
+
+/// Splits an output into the blocks starting at each match of a pattern, e.g. the
+/// interfaces of `show interfaces`.
+pub(crate) fn split_before<'a>(output: &'a str, pattern: &str) -> Vec<&'a str> {
+    let starts: Vec<usize> = Regex::new(pattern)
+        .expect("Invalid block regex")
+        .find_iter(output)
+        .map(|start| start.start())
+        .collect();
+
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&output.len()]))
+        .map(|(&start, &end)| &output[start..end])
+        .collect()
+}
+
+/// Takes the counters of an interface detail, with the discards parsed separately.
+pub(crate) fn counters(detail: InterfaceDetail, discards: (u64, u64)) -> InterfaceCounters {
+    InterfaceCounters {
+        interface: detail.name,
+        input_packets: detail.input_packets,
+        input_bytes: detail.input_bytes,
+        input_errors: detail.input_errors,
+        input_discards: discards.0,
+        output_packets: detail.output_packets,
+        output_bytes: detail.output_bytes,
+        output_errors: detail.output_errors,
+        output_discards: discards.1,
+    }
+}
+
+/// Builds a spanning tree port, expanding the abbreviated roles and states such as
+/// `Desg`, `DESI`, `FWD` or `BLK`.
+pub(crate) fn stp_port(interface: &str, role: &str, state: &str) -> StpPort {
+    let role = match role.to_lowercase().as_str() {
+        "desg" | "desi" => "designated".to_string(),
+        "altn" | "alte" => "alternate".to_string(),
+        "back" => "backup".to_string(),
+        "mast" | "mstr" => "master".to_string(),
+        "disa" | "disb" => "disabled".to_string(),
+        role => role.to_string(),
+    };
+    let state = match state.to_lowercase().as_str() {
+        "fwd" => "forwarding".to_string(),
+        "blk" => "blocking".to_string(),
+        "lrn" => "learning".to_string(),
+        "lis" => "listening".to_string(),
+        "bkn" | "bkn*" => "broken".to_string(),
+        state => state.to_string(),
+    };
+
+    StpPort {
+        interface: interface.to_string(),
+        role,
+        state,
+    }
+}
+
+/// Parses the MSTP listings of VRP and Comware: the `[CIST Global Info]` and
+/// `[MSTI 1 Global Info]` sections of `display stp` with the bridge and root IDs as
+/// `32768.0011-2233-4455`, and the `MSTID Port Role State` rows of `display stp brief`.
+pub(crate) fn stp_instances(detail: &str, brief: &str) -> Vec<StpInstance> {
+    let mut instances: Vec<StpInstance> =
+        split_before(detail, r"\[(?:CIST|MSTI \d+) Global Info\]")
+            .into_iter()
+            .map(|block| {
+                let id = capture(block, r"^\[MSTI (\d+)").unwrap_or_default();
+                let root =
+                    field(block, "CIST Root/ERPC").or_else(|| field(block, "MSTI RegRoot/IRPC"));
+                let bridge = field(block, "CIST Bridge")
+                    .or_else(|| field(block, "MSTI Bridge ID"))
+                    .or_else(|| field(block, "Bridge ID"));
+
+                let id_of = |text: Option<String>| {
+                    let text = text?;
+                    let (priority, address) = text.split('/').next()?.trim().split_once('.')?;
+                    Some((priority.parse().ok(), mac(address)))
+                };
+                let root = id_of(root);
+
+                StpInstance {
+                    id,
+                    root_priority: root.as_ref().and_then(|(priority, _)| *priority),
+                    root_mac: root
+                        .as_ref()
+                        .map(|(_, mac)| mac.clone())
+                        .unwrap_or_default(),
+                    is_root: root.is_some() && root == id_of(bridge),
+                    ports: Vec::new(),
+                }
+            })
+            .collect();
+
+    for line in brief.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [id, interface, role, state, ..] = fields[..] else {
+            continue;
+        };
+        let Ok(id) = id.parse() else {
+            continue;
+        };
+
+        if !instances.iter().any(|instance| instance.id == id) {
+            instances.push(StpInstance {
+                id,
+                ..Default::default()
+            });
+        }
+
+        if let Some(instance) = instances.iter_mut().find(|instance| instance.id == id) {
+            instance.ports.push(stp_port(interface, role, state));
+        }
+    }
+
+    instances
+}
+
+/// Parses `display acl all` of VRP and Comware: `Basic ACL 2000, 2 rules` or
+/// `Advanced IPv4 ACL 3000, named web, 1 rule,` headers and their
+/// `rule 5 permit tcp source 10.0.0.0 0.0.0.255 destination-port eq www (3 matched)` rules.
+pub(crate) fn acl_rules(output: &str) -> Vec<Acl> {
+    let header = Regex::new(r"^(\w+) (?:IPv4 )?ACL (\d+)(?:, named (\S+?),)?")
+        .expect("Invalid ACL header regex");
+    let rule =
+        Regex::new(r"^\s*rule (\d+) (permit|deny)\b(.*?)(?:\((\d+) (?:times )?matched\))?\s*$")
+            .expect("Invalid ACL rule regex");
+    let mut acls: Vec<Acl> = Vec::new();
+
+    for line in output.lines() {
+        if let Some(caps) = header.captures(line) {
+            acls.push(Acl {
+                name: caps
+                    .get(3)
+                    .or(caps.get(2))
+                    .map_or("", |name| name.as_str())
+                    .to_string(),
+                kind: caps[1].to_lowercase(),
+                rules: Vec::new(),
+            });
+        } else if let (Some(caps), Some(acl)) = (rule.captures(line), acls.last_mut()) {
+            let mut tokens = caps[3].split_whitespace().peekable();
+            let protocol = tokens
+                .next_if(|token| !token.contains('-') && !["source", "destination"].contains(token))
+                .map(str::to_lowercase)
+                .filter(|protocol| protocol != "ip");
+
+            let mut rule = AclRule {
+                sequence: caps[1].parse().ok(),
+                action: if &caps[2] == "permit" {
+                    AclAction::Permit
+                } else {
+                    AclAction::Deny
+                },
+                protocol,
+                source: "any".to_string(),
+                destination: "any".to_string(),
+                source_port: None,
+                destination_port: None,
+                hits: caps.get(4).and_then(|hits| hits.as_str().parse().ok()),
+            };
+
+            while let Some(keyword) = tokens.next() {
+                let arguments = match keyword {
+                    "source" | "destination" => 2,
+                    "source-port" | "destination-port" => match tokens.peek() {
+                        Some(&"range") => 3,
+                        _ => 2,
+                    },
+                    _ => continue,
+                };
+                let value = tokens
+                    .by_ref()
+                    .take(arguments)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                match keyword {
+                    "source" => rule.source = value,
+                    "destination" => rule.destination = value,
+                    "source-port" => rule.source_port = Some(value),
+                    _ => rule.destination_port = Some(value),
+                }
+            }
+
+            acl.rules.push(rule);
+        }
+    }
+
+    acls
+}
+
+/// Parses the `dir` listing of IOS, VRP and Comware: `2 -rw- 43,136 Aug 16 2021 15:34:46
+/// ifindex.dat` rows and a `1,007,616 KB total (765,244 KB free)` footer.
+pub(crate) fn filesystem(output: &str) -> Option<Filesystem> {
+    let row = Regex::new(
+        r"(?m)^\s*\d+\s+([-d])[-rwx]+\s+([\d,]+|-)\s+(\w{3} +\d+ \d{4} [\d:]+(?: [+-][\d:]+)?)\s+(\S.*?)\s*$",
+    )
+    .expect("Invalid file regex");
+    let footer = Regex::new(r"([\d,]+) (bytes|KB) total \(([\d,]+) (?:bytes|KB) free\)")
+        .expect("Invalid filesystem regex");
+
+    let caps = footer.captures(output)?;
+    let unit = if &caps[2] == "KB" { 1024 } else { 1 };
+    let bytes = |text: &str| text.replace(',', "").parse::<u64>().ok();
+
+    Some(Filesystem {
+        files: row
+            .captures_iter(output)
+            .map(|caps| FileEntry {
+                name: caps[4].to_string(),
+                size: bytes(&caps[2]),
+                modified: caps[3].to_string(),
+                is_dir: &caps[1] == "d",
+            })
+            .collect(),
+        total: bytes(&caps[1])? * unit,
+        free: bytes(&caps[3])? * unit,
+    })
+}
+
+/// Three-letter month names, as in `show clock` and `dir` listings.
+pub(crate) const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Returns the number of a three-letter month name.
+pub(crate) fn month(name: &str) -> Option<u8> {
+    MONTHS
+        .iter()
+        .position(|month| month.eq_ignore_ascii_case(name))
+        .map(|index| index as u8 + 1)
+}
+
+/// Parses traceroute output of IOS, VRP, Comware, Junos and Linux: `1 10.1.1.1 4 msec`
+/// or `1  gw (10.1.1.1)  0.512 ms` lines, IOS printing further addresses of a hop on
+/// indented lines without TTL.
+pub(crate) fn hops(output: &str) -> Vec<Hop> {
+    let rtt = Regex::new(r"(\d+(?:\.\d+)?) ?(?:ms|msec)\b").expect("Invalid RTT regex");
+    let mut hops: Vec<Hop> = Vec::new();
+
+    for line in output.lines() {
+        let mut tokens = line.split_whitespace().peekable();
+        let ttl = tokens.peek().and_then(|ttl| ttl.parse().ok());
+
+        let hop = match (ttl, hops.last_mut()) {
+            (Some(ttl), _) => {
+                tokens.next();
+                hops.push(Hop {
+                    ttl,
+                    ..Default::default()
+                });
+                hops.last_mut().expect("Hop just pushed")
+            }
+            (None, Some(hop)) if line.starts_with(char::is_whitespace) => hop,
+            _ => continue,
+        };
+
+        for addr in tokens.map(|token| token.trim_matches(|c| matches!(c, '(' | ')' | ','))) {
+            if addr.parse::<std::net::IpAddr>().is_ok()
+                && !hop.addrs.iter().any(|known| known == addr)
+            {
+                hop.addrs.push(addr.to_string());
+            }
+        }
+        hop.rtts.extend(
+            rtt.captures_iter(line)
+                .filter_map(|caps| caps[1].parse::<f64>().ok()),
+        );
+    }
+
+    hops
+}
+
+/// Compares two configurations line by line, ignoring blank lines, `#` separators and
+/// `!` comments, which carry timestamps such as `! Last configuration change at ...`.
+pub(crate) fn configs_differ(running: &str, startup: &str) -> bool {
+    fn significant(config: &str) -> impl Iterator<Item = &str> {
+        config
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.trim().is_empty() && line.trim() != "#" && !line.starts_with('!'))
+    }
+
+    !significant(running).eq(significant(startup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed() {
+        assert_eq!(speed_mbps("a-1000"), Some(1000));
+        assert_eq!(speed_mbps("10G"), Some(10000));
+        assert_eq!(speed_mbps("1G(a)"), Some(1000));
+        assert_eq!(speed_mbps("100M"), Some(100));
+        assert_eq!(speed_mbps("auto"), None);
+
+        assert_eq!(duplex("a-full"), Some(Duplex::Full));
+        assert_eq!(duplex("F(a)"), Some(Duplex::Full));
+        assert_eq!(duplex("A"), Some(Duplex::Auto));
+
+        assert_eq!(duration("00:01:33"), Some(Duration::from_secs(93)));
+        assert_eq!(duration("37"), Some(Duration::from_secs(37)));
+        assert_eq!(duration("never"), None);
+
+        assert_eq!(vlan_list("1,10-12,20"), [1, 10, 11, 12, 20]);
+        assert!(vlan_list("none").is_empty());
+
+        assert_eq!(
+            component_kind("Cisco ISR4331 Chassis"),
+            ComponentKind::Chassis
+        );
+        assert_eq!(component_kind("PWR_1"), ComponentKind::PowerSupply);
+        assert_eq!(component_kind(" Fan 1"), ComponentKind::Fan);
+        assert_eq!(component_kind("Slot 1 CPU 0"), ComponentKind::Module);
+
+        assert_eq!(
+            threshold_alarm("rx power", Some(-25.3), Some(-14.4), Some(1.0)).as_deref(),
+            Some("rx power low alarm")
+        );
+        assert_eq!(
+            threshold_alarm("rx power", Some(-3.1), Some(-14.4), None),
+            None
+        );
+
+        assert_eq!(capture::<u32>("MTU 1500 bytes", r"MTU (\d+)"), Some(1500));
+        assert_eq!(capture::<u32>("MTU 1500 bytes", r"BW (\d+)"), None);
+        assert_eq!(
+            field(" System name         : core-sw1\n", "System name").as_deref(),
+            Some("core-sw1")
+        );
+
+        assert_eq!(mac("0011.22AA.4455"), "00:11:22:aa:44:55");
+        assert_eq!(mac("0011-22aa-4455"), "00:11:22:aa:44:55");
+        assert_eq!(mac("Incomplete"), "incomplete");
+        assert!(is_mac("0011.22AA.4455"));
+        assert!(!is_mac("GigabitEthernet0/0/1"));
+
+        let header = "Interface   Status   Description";
+        let offset = column(header, "Description").unwrap();
+        assert_eq!(
+            from_column("Gi0/1       up       To core ", offset),
+            "To core"
+        );
+        assert_eq!(from_column("Gi0/2       down", offset), "");
+    }
+
+    #[test]
+    fn test_routing_table() {
+        let output = "\
+Route Flags: R - relay, D - download to fib
+------------------------------------------------------------------------------
+Routing Tables: Public
+         Destinations : 3        Routes : 4
+
+Destination/Mask    Proto   Pre  Cost      Flags NextHop         Interface
+
+        0.0.0.0/0   Static  60   0           RD   10.0.0.1        Vlanif10
+       10.0.0.0/24  Direct  0    0           D    10.0.0.2        Vlanif10
+      10.1.0.0/24   OSPF    10   2           D    10.0.0.3        Vlanif10
+                    OSPF    10   2           D    10.0.0.4        Vlanif20";
+        let routes = routing_table(output);
+        assert_eq!(routes.len(), 4);
+        assert_eq!(routes[0].prefix, "0.0.0.0/0");
+        assert_eq!(routes[0].preference, Some(60));
+        assert_eq!(routes[1].protocol, "connected");
+        assert_eq!(routes[3].prefix, "10.1.0.0/24");
+        assert_eq!(routes[3].next_hop.as_deref(), Some("10.0.0.4"));
+        assert_eq!(routes[3].interface.as_deref(), Some("Vlanif20"));
+        assert_eq!(routes[3].metric, Some(2));
+
+        let output = "\
+Destination/Mask   Proto   Pre Cost        NextHop         Interface
+10.1.0.0/24        O_INTRA 10  2           10.0.0.3        Vlan10";
+        assert_eq!(routing_table(output)[0].protocol, "o_intra");
+    }
+
+    #[test]
+    fn test_uptime() {
+        let output = "cisco-sw1 uptime is 3 weeks, 2 days, 4 hours, 5 minutes\n";
+        assert_eq!(
+            uptime(output),
+            Some(Duration::from_secs(23 * 86400 + 4 * 3600 + 5 * 60))
+        );
+
+        let output = "H3C S5130-28S-HI uptime is 0 weeks, 1 day, 2 hours, 3 minutes";
+        assert_eq!(uptime(output), Some(Duration::from_secs(93780)));
+
+        let output = "System uptime                  : 5:02:03:04\n";
+        assert_eq!(uptime(output), Some(Duration::from_secs(439384)));
+
+        assert_eq!(uptime("Software version 1.0"), None);
+    }
+
+    #[test]
+    fn test_prompt_hostname() {
+        let output = "\r\nLast login: Mon Mar  4 10:00:00 2024\r\ncore-sw1#";
+        assert_eq!(prompt_hostname(output).as_deref(), Some("core-sw1"));
+        assert_eq!(
+            prompt_hostname("core-sw1(config)#").as_deref(),
+            Some("core-sw1")
+        );
+        assert_eq!(
+            prompt_hostname("Info: Welcome\r\n<HUAWEI>").as_deref(),
+            Some("HUAWEI")
+        );
+        assert_eq!(prompt_hostname("[~CE6850]").as_deref(), Some("CE6850"));
+        assert_eq!(prompt_hostname("\r\n"), None);
+    }
+
+    #[test]
+    fn test_transceiver_types() {
+        let output = "\
+XGigabitEthernet0/0/1 transceiver information:
+-------------------------------------------------------------
+Common information:
+  Transceiver Type                      :10GBASE_SR_SFP
+  Connector Type                        :LC
+  Wavelength(nm)                        :850
+-------------------------------------------------------------
+XGigabitEthernet0/0/2 transceiver information:
+  Transceiver Type                      :1000_BASE_LX_SFP";
+        let types = transceiver_types(output);
+        assert_eq!(types.len(), 2);
+        assert_eq!(types["XGigabitEthernet0/0/1"], "10GBASE_SR_SFP");
+        assert_eq!(types["XGigabitEthernet0/0/2"], "1000_BASE_LX_SFP");
+    }
+
+    #[test]
+    fn test_split_before() {
+        let output = "header\nGi0/1 is up\n  counters\nGi0/2 is down\n";
+        let blocks = split_before(output, r"(?m)^\S+ is ");
+        assert_eq!(blocks, ["Gi0/1 is up\n  counters\n", "Gi0/2 is down\n"]);
+        assert!(split_before("", r"(?m)^\S+ is ").is_empty());
+    }
+
+    #[test]
+    fn test_stp_instances() {
+        let detail = "\
+-------[CIST Global Info][Mode MSTP]-------
+CIST Bridge         :32768.00e0-fc12-3456
+Config Times        :Hello 2s MaxAge 20s FwDly 15s MaxHop 20
+CIST Root/ERPC      :4096.0011-2233-4455 / 20000
+CIST RegRoot/IRPC   :32768.00e0-fc12-3456 / 0
+-------[MSTI 1 Global Info]-------
+MSTI Bridge ID      :4096.00e0-fc12-3456
+MSTI RegRoot/IRPC   :4096.00e0-fc12-3456 / 0";
+        let brief = "\
+ MSTID  Port                        Role  STP State     Protection
+   0    GigabitEthernet0/0/1        DESI  FORWARDING      NONE
+   0    GigabitEthernet0/0/24       ROOT  FORWARDING      NONE
+   1    GigabitEthernet0/0/24       MAST  FORWARDING      NONE";
+        let instances = stp_instances(detail, brief);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].id, 0);
+        assert_eq!(instances[0].root_priority, Some(4096));
+        assert_eq!(instances[0].root_mac, "00:11:22:33:44:55");
+        assert!(!instances[0].is_root);
+        assert_eq!(instances[0].ports.len(), 2);
+        assert_eq!(instances[0].ports[0].role, "designated");
+        assert_eq!(instances[0].ports[0].state, "forwarding");
+        assert_eq!(instances[1].id, 1);
+        assert!(instances[1].is_root);
+        assert_eq!(instances[1].ports[0].role, "master");
+    }
+
+    #[test]
+    fn test_acl_rules() {
+        let output = "\
+ Total quantity of nonempty ACL number is 2
+
+Basic ACL 2000, 2 rules
+Acl's step is 5
+ rule 5 permit source 10.0.0.0 0.0.0.255 (10 matched)
+ rule 10 deny
+
+Advanced IPv4 ACL 3000, named web, 1 rule,
+ACL's step is 5, start ID is 0
+ rule 0 permit tcp source 10.0.0.0 0.0.0.255 destination 10.1.0.5 0 destination-port eq www (3 times matched)";
+        let acls = acl_rules(output);
+        assert_eq!(acls.len(), 2);
+        assert_eq!(acls[0].name, "2000");
+        assert_eq!(acls[0].kind, "basic");
+        assert_eq!(acls[0].rules.len(), 2);
+        assert_eq!(acls[0].rules[0].sequence, Some(5));
+        assert_eq!(acls[0].rules[0].source, "10.0.0.0 0.0.0.255");
+        assert_eq!(acls[0].rules[0].hits, Some(10));
+        assert_eq!(acls[0].rules[1].action, AclAction::Deny);
+        assert_eq!(acls[0].rules[1].protocol, None);
+        assert_eq!(acls[0].rules[1].hits, None);
+
+        let rule = &acls[1].rules[0];
+        assert_eq!(acls[1].name, "web");
+        assert_eq!(rule.protocol.as_deref(), Some("tcp"));
+        assert_eq!(rule.destination, "10.1.0.5 0");
+        assert_eq!(rule.destination_port.as_deref(), Some("eq www"));
+        assert_eq!(rule.hits, Some(3));
+    }
+
+    #[test]
+    fn test_filesystem() {
+        let output = "\
+Directory of flash:/
+
+  Idx  Attr     Size(Byte)  Date        Time(LMT)  FileName
+    0  -rw-         14,791  Sep 14 2021 10:25:05   private-data.txt
+    1  drw-              -  Sep 14 2021 10:24:11   logfile
+    2  -rw-    175,006,208  Jul 20 2021 15:33:07   S5735-L-V200R021C00SPC100.cc
+
+1,007,616 KB total (765,244 KB free)";
+        let listing = filesystem(output).unwrap();
+        assert_eq!(listing.files.len(), 3);
+        assert_eq!(listing.files[0].name, "private-data.txt");
+        assert_eq!(listing.files[0].size, Some(14791));
+        assert_eq!(listing.files[0].modified, "Sep 14 2021 10:25:05");
+        assert!(listing.files[1].is_dir);
+        assert_eq!(listing.files[1].size, None);
+        assert_eq!(listing.total, 1_007_616 * 1024);
+        assert_eq!(listing.free, 765_244 * 1024);
+
+        let output = "\
+Directory of flash:/
+
+    2  -rwx        1048  Mar 1 1993 00:01:38 +00:00  config.text
+    3  drwx         512  Mar 1 1993 00:04:21 +00:00  html
+
+32514048 bytes total (11071488 bytes free)";
+        let listing = filesystem(output).unwrap();
+        assert_eq!(listing.files[0].name, "config.text");
+        assert_eq!(listing.files[0].modified, "Mar 1 1993 00:01:38 +00:00");
+        assert!(listing.files[1].is_dir);
+        assert_eq!(listing.total, 32514048);
+        assert_eq!(listing.free, 11071488);
+    }
+
+    #[test]
+    fn test_hops() {
+        let output = "\
+Type escape sequence to abort.
+Tracing the route to 10.0.0.1
+VRF info: (vrf in name/id, vrf out name/id)
+  1 10.1.1.1 4 msec 0 msec 4 msec
+  2 10.2.2.2 [AS 65000] 8 msec
+    10.2.2.3 8 msec 8 msec
+  3  *  *  *
+  4 10.0.0.1 12 msec *  8 msec";
+        let traced = hops(output);
+        assert_eq!(traced.len(), 4);
+        assert_eq!(traced[0].ttl, 1);
+        assert_eq!(traced[0].addrs, ["10.1.1.1"]);
+        assert_eq!(traced[0].rtts, [4.0, 0.0, 4.0]);
+        assert_eq!(traced[1].addrs, ["10.2.2.2", "10.2.2.3"]);
+        assert_eq!(traced[1].rtts.len(), 3);
+        assert!(traced[2].addrs.is_empty());
+        assert_eq!(traced[3].rtts, [12.0, 8.0]);
+
+        let output = "\
+traceroute to 10.0.0.1 (10.0.0.1), 30 hops max, 60 byte packets
+ 1  _gateway (192.168.1.1)  0.512 ms  0.470 ms  0.455 ms
+ 2  10.0.0.2  1.100 ms 10.0.0.3  1.300 ms *";
+        let traced = hops(output);
+        assert_eq!(traced.len(), 2);
+        assert_eq!(traced[0].addrs, ["192.168.1.1"]);
+        assert_eq!(traced[0].rtts, [0.512, 0.47, 0.455]);
+        assert_eq!(traced[1].addrs, ["10.0.0.2", "10.0.0.3"]);
+    }
+
+    #[test]
+    fn test_configs_differ() {
+        let running = "\
+!Software Version V200R021C00SPC100
+!Last configuration was updated at 2026-10-14 10:15:30+08:00
+#
+sysname SW1
+#
+return";
+        let startup = "\
+!Software Version V200R021C00SPC100
+!Last configuration was updated at 2026-10-13 09:00:00+08:00
+#
+sysname SW1
+return";
+        assert!(!configs_differ(running, startup));
+        assert!(configs_differ(running, &startup.replace("SW1", "SW2")));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::device::NetworkDevice;
+use crate::error::Error;
+use crate::ConnectConfig;
+
+/// Connects a driver registered at runtime, e.g.
+/// `|addr, user, pass, config| Ok(AcmeSSH::connect(addr, user, pass, config)?.into_dyn())`.
+pub type VendorFactory = dyn Fn(
+        &[SocketAddr],
+        Option<&str>,
+        Option<&str>,
+        ConnectConfig<'_>,
+    ) -> Result<Box<dyn NetworkDevice>, Error>
+    + Send
+    + Sync;
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<VendorFactory>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<VendorFactory>>>> = OnceLock::new();
+
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Registers an external driver under a case-insensitive name, replacing any driver
+/// registered before under that name.
+///
+/// Registered names take precedence over the built-in vendors in `connect_named`.
+pub fn register_vendor<F>(name: &str, factory: F)
+where
+    F: Fn(
+            &[SocketAddr],
+            Option<&str>,
+            Option<&str>,
+            ConnectConfig<'_>,
+        ) -> Result<Box<dyn NetworkDevice>, Error>
+        + Send
+        + Sync
+        + 'static,
+{
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_lowercase(), Arc::new(factory));
+}
+
+pub(crate) fn lookup(name: &str) -> Option<Arc<VendorFactory>> {
+    registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&name.to_lowercase())
+        .cloned()
+}
+
+pub(crate) fn unknown_vendor(name: &str) -> Error {
+    Error::Generic(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Unknown vendor '{}'", name),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_named;
+
+    #[test]
+    fn test_register_vendor() {
+        register_vendor("Acme", |addrs, username, _, _| {
+            assert_eq!(addrs.len(), 1);
+            assert_eq!(username, Some("admin"));
+
+            Err(Error::Disconnected)
+        });
+
+        let result = connect_named(
+            "acme",
+            "127.0.0.1:22",
+            Some("admin"),
+            None,
+            Default::default(),
+        );
+        assert!(matches!(result, Err(Error::Disconnected)));
+
+        let result = connect_named("unknown", "127.0.0.1:22", None, None, Default::default());
+        assert!(matches!(result, Err(Error::Generic(_))));
+    }
+}
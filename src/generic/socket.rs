@@ -0,0 +1,400 @@
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Local address and port range the management connection is opened from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindAddress {
+    /// Source IP, the unspecified address of the remote family is used if unset.
+    pub ip: Option<IpAddr>,
+    /// Source ports tried in order, an ephemeral port is used if unset.
+    pub ports: Option<RangeInclusive<u16>>,
+}
+
+impl BindAddress {
+    /// Binds to the given source IP with an ephemeral port.
+    pub fn ip(ip: IpAddr) -> Self {
+        Self {
+            ip: Some(ip),
+            ports: None,
+        }
+    }
+
+    fn local_ip(&self, remote: &SocketAddr) -> IpAddr {
+        self.ip.unwrap_or(match remote {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        })
+    }
+}
+
+/// Opens a TCP connection to `remote` from the configured local address.
+///
+/// Every port of the range is tried until one is free.
+pub(crate) fn connect_from(
+    bind: &BindAddress,
+    remote: &SocketAddr,
+    timeout: Option<Duration>,
+) -> io::Result<TcpStream> {
+    let ip = bind.local_ip(remote);
+
+    if ip.is_ipv4() != remote.is_ipv4() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Bind address {} doesn't match the family of {}", ip, remote),
+        ));
+    }
+
+    let ports = bind.ports.clone().unwrap_or(0..=0);
+    let mut last_error = None;
+
+    for port in ports {
+        match sys::connect(&SocketAddr::new(ip, port), remote, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty bind port range")))
+}
+
+/// Creates a pair of connected loopback sockets.
+pub(crate) fn loopback_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let local = TcpStream::connect(listener.local_addr()?)?;
+
+    loop {
+        let (stream, peer) = listener.accept()?;
+
+        // Refuse anything but our own end
+        if peer == local.local_addr()? {
+            return Ok((local, stream));
+        }
+    }
+}
+
+/// Thread pumping a caller-provided stream to a loopback socket, stopped and joined
+/// when dropped.
+pub(crate) struct Bridge {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Bridges a stream to a loopback socket, as libssh2 needs a real socket to work on.
+///
+/// A single thread owns the stream and alternates between both directions, so the
+/// stream must be non-blocking or time out reads, e.g. with `set_read_timeout` on its
+/// socket, for data to be sent while the other side is silent.
+pub(crate) fn bridge<S: Read + Write + Send + 'static>(
+    mut stream: S,
+) -> io::Result<(TcpStream, Bridge)> {
+    let (local, mut remote) = loopback_pair()?;
+    remote.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+
+    let thread = thread::spawn(move || {
+        let mut buf = [0u8; 16 * 1024];
+        let mut to_socket: Vec<u8> = Vec::new();
+
+        let pending = |e: &io::Error| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+            )
+        };
+
+        loop {
+            // What the session wrote is still sent once stopping, e.g. its disconnect
+            let stopping = stopped.load(Ordering::SeqCst);
+            let mut idle = true;
+
+            match remote.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => {
+                    if stream.write_all(&buf[..size]).and(stream.flush()).is_err() {
+                        break;
+                    }
+                    idle = false;
+                }
+                Err(ref e) if pending(e) => {}
+                Err(_) => break,
+            }
+
+            if stopping {
+                if idle {
+                    break;
+                }
+                continue;
+            }
+
+            if to_socket.is_empty() {
+                match stream.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(size) => to_socket.extend_from_slice(&buf[..size]),
+                    Err(ref e) if pending(e) => {}
+                    Err(_) => break,
+                }
+            }
+
+            if !to_socket.is_empty() {
+                match remote.write(&to_socket) {
+                    Ok(size) => {
+                        to_socket.drain(..size);
+                        idle = false;
+                    }
+                    Err(ref e) if pending(e) => {}
+                    Err(_) => break,
+                }
+            }
+
+            if idle {
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+
+        let _ = remote.shutdown(Shutdown::Both);
+    });
+
+    Ok((
+        local,
+        Bridge {
+            stop,
+            thread: Some(thread),
+        },
+    ))
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::io;
+    use std::mem;
+    use std::net::{SocketAddr, TcpStream};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::{Duration, Instant};
+
+    fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    /// Calls `f` with the C representation of an address.
+    fn with_sockaddr<T>(
+        addr: &SocketAddr,
+        f: impl FnOnce(*const libc::sockaddr, libc::socklen_t) -> T,
+    ) -> T {
+        match addr {
+            SocketAddr::V4(addr) => {
+                // SAFETY: sockaddr_in is plain old data, all zeroes is a valid value.
+                let mut sin: libc::sockaddr_in = unsafe { mem::zeroed() };
+                sin.sin_family = libc::AF_INET as libc::sa_family_t;
+                sin.sin_port = addr.port().to_be();
+                sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+
+                f(&sin as *const _ as *const _, mem::size_of_val(&sin) as _)
+            }
+            SocketAddr::V6(addr) => {
+                // SAFETY: sockaddr_in6 is plain old data, all zeroes is a valid value.
+                let mut sin6: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+                sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sin6.sin6_port = addr.port().to_be();
+                sin6.sin6_addr.s6_addr = addr.ip().octets();
+                sin6.sin6_flowinfo = addr.flowinfo();
+                sin6.sin6_scope_id = addr.scope_id();
+
+                f(&sin6 as *const _ as *const _, mem::size_of_val(&sin6) as _)
+            }
+        }
+    }
+
+    /// Waits for a non-blocking connect to finish, retrying interrupted polls.
+    fn wait_writable(fd: libc::c_int, timeout: Option<Duration>) -> io::Result<()> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let timeout_ms = match deadline {
+                None => -1,
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "Connection timed out",
+                        ));
+                    }
+                    remaining.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int
+                }
+            };
+
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: libc::POLLOUT,
+                revents: 0,
+            };
+
+            // SAFETY: pollfd points to a single valid entry.
+            match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout_ms) }) {
+                Ok(0) => {}
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Binds a socket to the local address before connecting it, which `std::net`
+    /// can't do; everything else goes through `TcpStream`.
+    pub(super) fn connect(
+        local: &SocketAddr,
+        remote: &SocketAddr,
+        timeout: Option<Duration>,
+    ) -> io::Result<TcpStream> {
+        let family = match remote {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+
+        // SAFETY: the new descriptor is checked and owned by the stream right away.
+        let fd = cvt(unsafe { libc::socket(family, libc::SOCK_STREAM, 0) })?;
+        let stream = TcpStream::from(unsafe { OwnedFd::from_raw_fd(fd) });
+        let fd = stream.as_raw_fd();
+
+        // SAFETY: the descriptor is valid for the lifetime of the stream.
+        cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+        // SAFETY: the address is valid for the given length during the call.
+        with_sockaddr(local, |addr, len| cvt(unsafe { libc::bind(fd, addr, len) }))?;
+
+        stream.set_nonblocking(true)?;
+
+        // SAFETY: the address is valid for the given length during the call.
+        match with_sockaddr(remote, |addr, len| {
+            cvt(unsafe { libc::connect(fd, addr, len) })
+        }) {
+            Ok(_) => {}
+            // An interrupted connect goes on in the background like a non-blocking one
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EINPROGRESS | libc::EINTR)) => {
+                wait_writable(fd, timeout)?
+            }
+            Err(e) => return Err(e),
+        }
+
+        if let Some(e) = stream.take_error()? {
+            return Err(e);
+        }
+        stream.set_nonblocking(false)?;
+
+        Ok(stream)
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::io;
+    use std::net::{SocketAddr, TcpStream};
+    use std::time::Duration;
+
+    pub(super) fn connect(
+        _local: &SocketAddr,
+        _remote: &SocketAddr,
+        _timeout: Option<Duration>,
+    ) -> io::Result<TcpStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Binding a source address is only supported on unix",
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn test_bridge() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        stream.set_read_timeout(Some(Duration::from_millis(20)))?;
+        let (mut server, _) = listener.accept()?;
+
+        let (mut bridged, worker) = bridge(stream)?;
+        bridged.write_all(b"ping")?;
+
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"ping");
+
+        server.write_all(b"pong")?;
+        bridged.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"pong");
+
+        // Dropping the bridge joins the thread, which closes the stream
+        drop(worker);
+        assert_eq!(server.read(&mut buf)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_from() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let remote = listener.local_addr()?;
+        let bind = BindAddress::ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let stream = connect_from(&bind, &remote, Some(Duration::from_secs(1)))?;
+        let (_, peer) = listener.accept()?;
+        assert_eq!(stream.local_addr()?, peer);
+        assert_eq!(peer.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        // A taken source port is skipped
+        let taken = TcpListener::bind("127.0.0.1:0")?;
+        let port = taken.local_addr()?.port();
+        let bind = BindAddress {
+            ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ports: Some(port..=port.saturating_add(16)),
+        };
+        let stream = connect_from(&bind, &remote, None)?;
+        assert_ne!(stream.local_addr()?.port(), port);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_connect_from_errors() -> anyhow::Result<()> {
+        let closed = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let bind = BindAddress::ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let error = connect_from(&bind, &closed, Some(Duration::from_secs(1))).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+
+        let error = connect_from(&bind, &closed, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+
+        let bind = BindAddress::ip("::1".parse()?);
+        let error = connect_from(&bind, &closed, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{ConnectConfig, PrivateKey};
+
+/// A jump host the connection is tunneled through, like OpenSSH `ProxyJump`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    pub host: String,
+    pub port: u16,
+    /// Login user on the jump host, the local user is used if unset like with OpenSSH.
+    pub user: Option<String>,
+    /// Identity files tried on the jump host, before the configured private key and the
+    /// ssh-agent. Jump hosts never get the device password.
+    pub identity_files: Vec<PathBuf>,
+}
+
+impl JumpHost {
+    /// Parses a `[user@]host[:port]` jump specification.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (user, host, port) = parse_spec(spec)?;
+
+        Some(Self {
+            host,
+            port: port.unwrap_or(22),
+            user,
+            identity_files: Vec::new(),
+        })
+    }
+}
+
+/// Settings resolved for one host from OpenSSH client configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostConfig {
+    pub host_name: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub identity_files: Vec<PathBuf>,
+    pub proxy_jump: Vec<JumpHost>,
+}
+
+impl HostConfig {
+    /// Address to pass to `connect`.
+    pub fn addr(&self) -> (&str, u16) {
+        (&self.host_name, self.port)
+    }
+
+    /// Applies the identity file and jump hosts to the config, keeping explicit settings.
+    pub fn apply<'a>(&self, mut config: ConnectConfig<'a>) -> ConnectConfig<'a> {
+        if config.private_key.is_none() {
+            config.private_key =
+                self.identity_files
+                    .iter()
+                    .find(|path| path.exists())
+                    .map(|path| PrivateKey::File {
+                        path: path.clone(),
+                        passphrase: None,
+                    });
+        }
+
+        if config.proxy_jump.is_empty() {
+            config.proxy_jump = self.proxy_jump.clone();
+        }
+
+        config
+    }
+}
+
+/// A parsed OpenSSH client configuration(`~/.ssh/config`).
+///
+/// Supports `Host` blocks with `*`, `?` and `!` patterns and the `HostName`, `Port`,
+/// `User`, `IdentityFile` and `ProxyJump` keywords. `Match` blocks are ignored. As with
+/// OpenSSH the first obtained value wins.
+#[derive(Debug, Clone, Default)]
+pub struct SshConfig {
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    /// Host patterns, `None` for `Match` blocks which never apply.
+    patterns: Option<Vec<String>>,
+    options: Vec<(String, String)>,
+}
+
+impl SshConfig {
+    /// Loads `~/.ssh/config`, empty if it doesn't exist.
+    pub fn load_default() -> io::Result<Self> {
+        match home_dir() {
+            Some(home) => match Self::load(home.join(".ssh").join("config")) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+                result => result,
+            },
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        // Options before the first Host line apply to every host
+        let mut blocks = vec![Block {
+            patterns: Some(vec!["*".to_string()]),
+            options: Vec::new(),
+        }];
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+                Some((keyword, value)) => (
+                    keyword.to_ascii_lowercase(),
+                    value.trim_start_matches(|c: char| c.is_whitespace() || c == '='),
+                ),
+                None => continue,
+            };
+            let value = value.trim();
+
+            match keyword.as_str() {
+                "host" => blocks.push(Block {
+                    patterns: Some(value.split_whitespace().map(unquote).collect()),
+                    options: Vec::new(),
+                }),
+                "match" => blocks.push(Block {
+                    patterns: None,
+                    options: Vec::new(),
+                }),
+                _ => {
+                    if let Some(block) = blocks.last_mut() {
+                        block.options.push((keyword, unquote(value)));
+                    }
+                }
+            }
+        }
+
+        Self { blocks }
+    }
+
+    /// Resolves the settings for a host alias, jump hosts through their own entries.
+    pub fn resolve(&self, host: &str) -> HostConfig {
+        let (mut config, proxy_jump) = self.lookup(host);
+
+        config.proxy_jump = match proxy_jump.as_deref() {
+            None | Some("none") => Vec::new(),
+            Some(spec) => spec
+                .split(',')
+                .filter_map(|spec| self.jump_host(spec))
+                .collect(),
+        };
+
+        config
+    }
+
+    /// Resolves a jump specification, the host entry filling what it leaves out.
+    fn jump_host(&self, spec: &str) -> Option<JumpHost> {
+        let (user, host, port) = parse_spec(spec)?;
+        let (config, _) = self.lookup(&host);
+
+        Some(JumpHost {
+            host: config.host_name,
+            port: port.unwrap_or(config.port),
+            user: user.or(config.user),
+            identity_files: config.identity_files,
+        })
+    }
+
+    /// Collects the settings of a host, with its `ProxyJump` value left unparsed.
+    fn lookup(&self, host: &str) -> (HostConfig, Option<String>) {
+        let mut host_name = None;
+        let mut port = None;
+        let mut user = None;
+        let mut identity_files = Vec::new();
+        let mut proxy_jump = None;
+
+        for block in &self.blocks {
+            let matched = block
+                .patterns
+                .as_ref()
+                .is_some_and(|patterns| matches_host(patterns, host));
+
+            if !matched {
+                continue;
+            }
+
+            for (keyword, value) in &block.options {
+                match keyword.as_str() {
+                    "hostname" => {
+                        host_name.get_or_insert_with(|| value.replace("%h", host));
+                    }
+                    "port" if port.is_none() => port = value.parse().ok(),
+                    "user" => {
+                        user.get_or_insert_with(|| value.clone());
+                    }
+                    "identityfile" => identity_files.push(value.clone()),
+                    "proxyjump" => {
+                        proxy_jump.get_or_insert_with(|| value.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let host_name = host_name.unwrap_or_else(|| host.to_string());
+        let port = port.unwrap_or(22);
+        let identity_files = identity_files
+            .iter()
+            .map(|path| expand_path(path, &host_name, port, user.as_deref()))
+            .collect();
+
+        let config = HostConfig {
+            host_name,
+            port,
+            user,
+            identity_files,
+            proxy_jump: Vec::new(),
+        };
+
+        (config, proxy_jump)
+    }
+}
+
+/// Splits a `[user@]host[:port]` jump specification, the port being unset if omitted.
+fn parse_spec(spec: &str) -> Option<(Option<String>, String, Option<u16>)> {
+    let spec = spec.trim().trim_start_matches("ssh://");
+    let (user, host) = match spec.rsplit_once('@') {
+        Some((user, host)) => (Some(user.to_string()), host),
+        None => (None, spec),
+    };
+
+    let (host, port) = if let Some(rest) = host.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => Some(port.parse().ok()?),
+            None => None,
+        };
+        (host, port)
+    } else {
+        match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().ok()?)),
+            None => (host, None),
+        }
+    };
+
+    if host.is_empty() {
+        return None;
+    }
+
+    Some((user, host.to_string(), port))
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// The local login name, which OpenSSH uses for hosts without a `User`.
+pub(crate) fn local_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Expands `~` and the `%d`, `%h`, `%p`, `%r` and `%%` tokens of a path.
+fn expand_path(path: &str, host: &str, port: u16, user: Option<&str>) -> PathBuf {
+    let home = home_dir().unwrap_or_default();
+    let home = home.to_string_lossy();
+
+    let path = match path.strip_prefix('~') {
+        Some(rest) => format!("{}{}", home, rest),
+        None => path.to_string(),
+    };
+
+    let mut expanded = String::new();
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('d') => expanded.push_str(&home),
+            Some('h') => expanded.push_str(host),
+            Some('p') => expanded.push_str(&port.to_string()),
+            Some('r') => expanded.push_str(user.unwrap_or_default()),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    PathBuf::from(expanded)
+}
+
+/// Matches a host against a pattern list, where any negated match rejects the host.
+fn matches_host(patterns: &[String], host: &str) -> bool {
+    let mut matched = false;
+
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(pattern) if wildcard_match(pattern, host) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, host),
+        }
+    }
+
+    matched
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+User fallback
+
+Host core-* !core-lab
+    HostName 10.0.%h.1
+    Port 2222
+    IdentityFile ~/.ssh/core_%r
+
+Host core-lab
+    HostName=192.168.1.1
+    ProxyJump ops@bastion:2200,[fe80::1]
+
+Host bastion
+    HostName 10.9.9.9
+    Port 2222
+    IdentityFile /keys/bastion
+
+Host *
+    User admin
+    Port 22
+"#;
+
+    #[test]
+    fn test_resolve() {
+        let config = SshConfig::parse(CONFIG);
+
+        let host = config.resolve("core-1");
+        assert_eq!(host.addr(), ("10.0.core-1.1", 2222));
+        assert_eq!(host.user.as_deref(), Some("fallback"));
+        assert!(host.identity_files[0].ends_with(".ssh/core_fallback"));
+        assert!(host.proxy_jump.is_empty());
+
+        let host = config.resolve("core-lab");
+        assert_eq!(host.addr(), ("192.168.1.1", 22));
+        assert_eq!(
+            host.proxy_jump,
+            vec![
+                JumpHost {
+                    host: "10.9.9.9".to_string(),
+                    port: 2200,
+                    user: Some("ops".to_string()),
+                    identity_files: vec![PathBuf::from("/keys/bastion")],
+                },
+                JumpHost {
+                    host: "fe80::1".to_string(),
+                    port: 22,
+                    user: Some("fallback".to_string()),
+                    identity_files: Vec::new(),
+                },
+            ]
+        );
+
+        let host = config.resolve("10.1.1.1");
+        assert_eq!(host.addr(), ("10.1.1.1", 22));
+    }
+}
@@ -0,0 +1,693 @@
+use std::time::Duration;
+
+/// Duplex mode of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplex {
+    Full,
+    Half,
+    Auto,
+}
+
+/// Interface summary from the brief interface listings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String,
+    pub admin_up: bool,
+    pub oper_up: bool,
+    /// Speed in Mbit/s, unset while negotiating or if not reported.
+    pub speed: Option<u64>,
+    pub duplex: Option<Duplex>,
+    pub description: String,
+    /// Addresses, with the prefix length where the listing shows it(`10.0.0.1/24`).
+    pub ips: Vec<String>,
+}
+
+/// State, counters and rates of a single interface.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterfaceDetail {
+    pub name: String,
+    pub description: String,
+    pub admin_up: bool,
+    pub oper_up: bool,
+    pub mtu: Option<u32>,
+    /// Speed in Mbit/s.
+    pub speed: Option<u64>,
+    pub duplex: Option<Duplex>,
+    /// Last link state change, as printed by the device(a timestamp or an age).
+    pub last_flap: Option<String>,
+    /// Input rate in bit/s, averaged over the load interval of the device.
+    pub input_rate: Option<u64>,
+    /// Output rate in bit/s, averaged over the load interval of the device.
+    pub output_rate: Option<u64>,
+    pub input_packets: u64,
+    pub input_bytes: u64,
+    pub input_errors: u64,
+    pub output_packets: u64,
+    pub output_bytes: u64,
+    pub output_errors: u64,
+}
+
+/// ARP cache entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArpEntry {
+    pub ip: String,
+    /// Lowercase colon separated address, e.g. `00:11:22:33:44:55`.
+    pub mac: String,
+    pub interface: String,
+    pub vrf: Option<String>,
+    /// Age in minutes, unset for static and local entries.
+    pub age: Option<u32>,
+}
+
+/// How a MAC address table entry was learned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacType {
+    Dynamic,
+    Static,
+    /// Secure, sticky or system entries.
+    Other,
+}
+
+/// MAC address table entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacEntry {
+    /// Lowercase colon separated address, e.g. `00:11:22:33:44:55`.
+    pub mac: String,
+    /// Unset for entries valid in all VLANs.
+    pub vlan: Option<u16>,
+    pub kind: MacType,
+    pub interface: String,
+}
+
+/// Neighbor discovered through LLDP.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LldpNeighbor {
+    pub local_port: String,
+    pub system_name: String,
+    pub chassis_id: String,
+    pub port_id: String,
+    pub port_description: String,
+    /// Enabled capabilities in lowercase, e.g. `bridge`, `router`.
+    pub capabilities: Vec<String>,
+    pub management_ip: Option<String>,
+}
+
+/// Routing table entry, one per next hop of an ECMP route.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteEntry {
+    /// Destination with prefix length, e.g. `10.0.0.0/24`.
+    pub prefix: String,
+    pub next_hop: Option<String>,
+    /// Lowercase protocol, e.g. `connected`, `static`, `ospf`, `bgp`.
+    pub protocol: String,
+    /// Administrative distance(preference).
+    pub preference: Option<u32>,
+    pub metric: Option<u32>,
+    pub interface: Option<String>,
+}
+
+/// BGP peer session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BgpPeer {
+    pub neighbor: String,
+    pub asn: u32,
+    /// Session state as printed, `Established` once up.
+    pub state: String,
+    /// Prefixes received, zero unless established.
+    pub prefixes: u32,
+    /// Time in the current state, as printed(`2d03h`, `00:01:02`).
+    pub uptime: String,
+}
+
+impl BgpPeer {
+    /// Checks whether the session is up.
+    pub fn is_established(&self) -> bool {
+        self.state.eq_ignore_ascii_case("established")
+    }
+}
+
+/// OSPF adjacency.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OspfNeighbor {
+    pub neighbor_id: String,
+    pub address: String,
+    pub interface: String,
+    /// State as printed, e.g. `FULL/DR` or `Full`.
+    pub state: String,
+    /// Time left before the adjacency is declared down.
+    pub dead_time: Option<Duration>,
+}
+
+/// Port member of a VLAN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VlanPort {
+    pub name: String,
+    pub tagged: bool,
+}
+
+/// VLAN with its member ports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vlan {
+    pub id: u16,
+    pub name: String,
+    pub ports: Vec<VlanPort>,
+}
+
+/// Kind of a hardware component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    Chassis,
+    /// Line cards, supervisors and stack members.
+    Module,
+    PowerSupply,
+    Fan,
+}
+
+/// Hardware component with its part number and serial number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryItem {
+    /// Slot or component name as printed, e.g. `Switch 1 - Power Supply A` or `Slot 1`.
+    pub name: String,
+    pub kind: ComponentKind,
+    pub description: String,
+    /// Part number(PID or board type).
+    pub model: String,
+    pub serial: String,
+}
+
+/// Chassis model and serial number with all installed components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    pub model: String,
+    pub serial: String,
+    /// Components in listing order, the chassis included.
+    pub items: Vec<InventoryItem>,
+}
+
+/// Kind of an environment sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    Temperature,
+    Fan,
+    PowerSupply,
+}
+
+/// Temperature, fan or power supply reading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    /// Sensor or unit name, prefixed with the slot, e.g. `Slot 1 hotspot 1`.
+    pub name: String,
+    pub kind: SensorKind,
+    /// Degrees Celsius, percent of full fan speed or watts, where reported.
+    pub value: Option<f64>,
+    pub lower_threshold: Option<f64>,
+    /// Alarm threshold, e.g. the red threshold of Cisco IOS.
+    pub upper_threshold: Option<f64>,
+    /// State as printed, e.g. `OK`, `Normal` or `Not Present`.
+    pub status: String,
+}
+
+impl Sensor {
+    pub(crate) fn new(name: &str, kind: SensorKind, status: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            value: None,
+            lower_threshold: None,
+            upper_threshold: None,
+            status: status.to_string(),
+        }
+    }
+
+    /// Checks whether the reading reached the upper threshold.
+    pub fn is_over_threshold(&self) -> bool {
+        matches!((self.value, self.upper_threshold), (Some(value), Some(upper)) if value >= upper)
+    }
+}
+
+/// CPU load and memory usage of the main CPU.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuMemory {
+    /// CPU load in percent over the last five seconds.
+    pub cpu_5s: Option<u8>,
+    /// CPU load in percent over the last minute.
+    pub cpu_1m: Option<u8>,
+    /// CPU load in percent over the last five minutes.
+    pub cpu_5m: Option<u8>,
+    /// Used memory in bytes.
+    pub memory_used: u64,
+    /// Free memory in bytes.
+    pub memory_free: u64,
+}
+
+/// NTP synchronization state of the system clock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NtpStatus {
+    pub synchronized: bool,
+    pub stratum: Option<u8>,
+    /// Address or ID of the reference clock, unset while unsynchronized.
+    pub reference: Option<String>,
+    /// Offset to the reference clock in milliseconds.
+    pub offset: Option<f64>,
+}
+
+/// Digital diagnostics of an optical transceiver.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transceiver {
+    pub interface: String,
+    /// Optic type as printed, e.g. `10GBase-SR` or `1000_BASE_SX_SFP`.
+    pub media: Option<String>,
+    /// Module temperature in degrees Celsius.
+    pub temperature: Option<f64>,
+    /// Transmit power in dBm.
+    pub tx_power: Option<f64>,
+    /// Receive power in dBm.
+    pub rx_power: Option<f64>,
+    /// Readings beyond their thresholds, e.g. `rx power low alarm`.
+    pub alarms: Vec<String>,
+}
+
+/// Power over Ethernet state of a port.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoePort {
+    pub interface: String,
+    pub enabled: bool,
+    /// Whether power is delivered to a powered device.
+    pub powered: bool,
+    /// IEEE power class of the powered device.
+    pub class: Option<u8>,
+    /// Power draw in watts.
+    pub power: Option<f64>,
+    /// Powered device type, where the listing shows it(`AIR-AP2802I-E-K9`).
+    pub device: Option<String>,
+}
+
+/// Cumulative traffic and error counters of an interface.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub interface: String,
+    pub input_packets: u64,
+    pub input_bytes: u64,
+    pub input_errors: u64,
+    pub input_discards: u64,
+    pub output_packets: u64,
+    pub output_bytes: u64,
+    pub output_errors: u64,
+    pub output_discards: u64,
+}
+
+/// Per-second rates of an interface between two counter snapshots.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceRates {
+    pub interface: String,
+    pub input_bps: f64,
+    pub output_bps: f64,
+    pub input_pps: f64,
+    pub output_pps: f64,
+    /// Input and output errors per second.
+    pub errors: f64,
+    /// Input and output discards per second.
+    pub discards: f64,
+}
+
+impl InterfaceCounters {
+    /// Computes the rates since an earlier snapshot of the same interface.
+    ///
+    /// A counter below its previous value was cleared and counts from zero.
+    pub fn rates_since(&self, previous: &InterfaceCounters, elapsed: Duration) -> InterfaceRates {
+        let seconds = elapsed.as_secs_f64();
+        let rate = |current: u64, previous: u64| {
+            if seconds == 0.0 {
+                return 0.0;
+            }
+
+            current.checked_sub(previous).unwrap_or(current) as f64 / seconds
+        };
+
+        InterfaceRates {
+            interface: self.interface.clone(),
+            input_bps: rate(self.input_bytes, previous.input_bytes) * 8.0,
+            output_bps: rate(self.output_bytes, previous.output_bytes) * 8.0,
+            input_pps: rate(self.input_packets, previous.input_packets),
+            output_pps: rate(self.output_packets, previous.output_packets),
+            errors: rate(self.input_errors, previous.input_errors)
+                + rate(self.output_errors, previous.output_errors),
+            discards: rate(self.input_discards, previous.input_discards)
+                + rate(self.output_discards, previous.output_discards),
+        }
+    }
+}
+
+/// Computes the rates of the interfaces present in both snapshots, taken `elapsed` apart.
+pub fn counter_rates(
+    previous: &[InterfaceCounters],
+    current: &[InterfaceCounters],
+    elapsed: Duration,
+) -> Vec<InterfaceRates> {
+    current
+        .iter()
+        .filter_map(|counters| {
+            let earlier = previous
+                .iter()
+                .find(|earlier| earlier.interface == counters.interface)?;
+
+            Some(counters.rates_since(earlier, elapsed))
+        })
+        .collect()
+}
+
+/// Port of a spanning tree instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StpPort {
+    pub interface: String,
+    /// Lowercase role, e.g. `root`, `designated`, `alternate`, `backup`.
+    pub role: String,
+    /// Lowercase state, e.g. `forwarding`, `discarding`, `learning`, `blocking`.
+    pub state: String,
+}
+
+/// Spanning tree instance, a VLAN with PVST or an MST instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StpInstance {
+    /// VLAN ID with PVST, MST instance ID otherwise.
+    pub id: u16,
+    pub root_priority: Option<u32>,
+    /// Lowercase colon separated address of the root bridge.
+    pub root_mac: String,
+    /// Whether this switch is the root bridge.
+    pub is_root: bool,
+    pub ports: Vec<StpPort>,
+}
+
+/// Negotiation protocol of a link aggregation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagProtocol {
+    Lacp,
+    Pagp,
+    /// Manually configured, without negotiation.
+    Static,
+}
+
+/// Member port of a link aggregation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LagMember {
+    pub interface: String,
+    /// Whether the port is bundled and forwarding.
+    pub active: bool,
+    /// State as printed, e.g. the `P` flag, `Up`, `Selected` or `S`.
+    pub state: String,
+}
+
+/// Link aggregation with its member ports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortChannel {
+    pub id: u32,
+    /// Aggregate interface, e.g. `Po1`, `Eth-Trunk1` or `Bridge-Aggregation1`.
+    pub name: String,
+    pub protocol: LagProtocol,
+    pub up: bool,
+    pub members: Vec<LagMember>,
+}
+
+/// Member of a switch stack(IRF, iStack or StackWise).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackMember {
+    pub id: u32,
+    /// Lowercase role, e.g. `master`, `active`, `standby`, `slave` or `member`.
+    pub role: String,
+    pub priority: Option<u32>,
+    /// Lowercase colon separated bridge address.
+    pub mac: String,
+    pub model: Option<String>,
+    /// State where reported, e.g. `Ready`.
+    pub state: Option<String>,
+}
+
+/// DHCP snooping binding of a client address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DhcpBinding {
+    pub ip: String,
+    /// Lowercase colon separated address, e.g. `00:11:22:33:44:55`.
+    pub mac: String,
+    pub vlan: Option<u16>,
+    pub interface: String,
+    /// Lease as printed, the remaining seconds(`86000`) or the expiry time.
+    pub lease: Option<String>,
+}
+
+/// Action of an ACL rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclAction {
+    Permit,
+    Deny,
+}
+
+/// Rule of an access list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclRule {
+    /// Sequence number or rule ID.
+    pub sequence: Option<u32>,
+    pub action: AclAction,
+    /// Lowercase protocol, unset for rules matching any IP traffic.
+    pub protocol: Option<String>,
+    /// Source match as printed, e.g. `any`, `host 10.0.0.1` or `10.0.0.0 0.0.0.255`.
+    pub source: String,
+    pub destination: String,
+    /// Source port match, e.g. `eq 80` or `range 1000 2000`.
+    pub source_port: Option<String>,
+    /// Destination port match, e.g. `eq www`.
+    pub destination_port: Option<String>,
+    /// Packets matched, unset if the listing does not count them.
+    pub hits: Option<u64>,
+}
+
+/// Numbered or named access list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    /// Name, or number for numbered lists.
+    pub name: String,
+    /// Lowercase type, e.g. `standard`, `extended`, `basic` or `advanced`.
+    pub kind: String,
+    pub rules: Vec<AclRule>,
+}
+
+/// Locally configured user account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalUser {
+    pub name: String,
+    /// Privilege level, where the account has one.
+    pub privilege: Option<u8>,
+    /// Role of the account on Comware, e.g. `network-admin`.
+    pub role: Option<String>,
+    /// Lowercase services the account may use, e.g. `ssh`, `telnet`, `terminal`.
+    pub services: Vec<String>,
+}
+
+/// Installed license or licensed feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct License {
+    pub feature: String,
+    /// State as reported by the device, e.g. `Active, In Use` or `Normal`.
+    pub state: String,
+    /// Expiry date or remaining period, `None` for permanent licenses.
+    pub expires: Option<String>,
+    /// Licensed count, e.g. users or APs, where the license is counted.
+    pub count: Option<u32>,
+}
+
+/// Access point managed by a wireless controller.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessPoint {
+    /// Numeric AP ID, on controllers that assign one.
+    pub id: Option<u32>,
+    pub mac: Option<String>,
+    pub name: String,
+    pub group: Option<String>,
+    pub ip: Option<String>,
+    pub model: String,
+    /// Short state as displayed, e.g. `nor` on VRP, `R/M` on Comware or `Up` on ArubaOS.
+    pub state: String,
+    /// Associated clients, where the AP listing shows them.
+    pub clients: Option<u32>,
+}
+
+/// Wireless client associated to an access point.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WirelessClient {
+    pub mac: String,
+    /// Authenticated user name, for 802.1X and portal clients.
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    /// Numeric ID of the AP, on controllers that assign one.
+    pub ap_id: Option<u32>,
+    pub ap_name: String,
+    pub ssid: String,
+    /// Radio band or mode as displayed, e.g. `5G`, `2.4GHz` or `802.11ac`.
+    pub band: String,
+    pub rssi: Option<i32>,
+    pub snr: Option<i32>,
+    pub vlan: Option<u16>,
+}
+
+/// RF state of one radio of an access point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Radio {
+    pub ap_name: String,
+    /// Radio number on the AP, or `0` for 2.4 GHz and `1` for 5 GHz where the listing
+    /// only names the band.
+    pub radio: u8,
+    pub band: Option<String>,
+    pub channel: Option<u16>,
+    /// Transmit power or EIRP in dBm.
+    pub power: Option<f64>,
+    /// Channel utilization in percent.
+    pub utilization: Option<u8>,
+    /// Noise floor in dBm.
+    pub noise_floor: Option<i32>,
+    pub clients: Option<u32>,
+}
+
+/// File or directory on a device filesystem.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileEntry {
+    pub name: String,
+    /// Size in bytes, `None` for directories.
+    pub size: Option<u64>,
+    /// Modification date as displayed, e.g. `Jul 12 2021 10:04:20`.
+    pub modified: String,
+    pub is_dir: bool,
+}
+
+/// Listing and capacity of a device filesystem, e.g. `flash:`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filesystem {
+    pub files: Vec<FileEntry>,
+    /// Total capacity in bytes.
+    pub total: u64,
+    /// Free space in bytes.
+    pub free: u64,
+}
+
+/// Calendar date and time of a device clock, in its local time zone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Builds the date and time of a Unix timestamp, e.g. to set a device clock to UTC.
+    pub fn from_timestamp(timestamp: i64) -> Self {
+        let (days, seconds) = (timestamp.div_euclid(86_400), timestamp.rem_euclid(86_400));
+
+        // Civil from days, shifted to eras starting on March 1st
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let of_era = days.rem_euclid(146_097);
+        let year_of_era = (of_era - of_era / 1_460 + of_era / 36_524 - of_era / 146_096) / 365;
+        let of_year = of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * of_year + 2) / 153;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: (of_year - (153 * shifted_month + 2) / 5 + 1) as u8,
+            hour: (seconds / 3_600) as u8,
+            minute: (seconds % 3_600 / 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    /// Returns the seconds since the Unix epoch, reading the time as UTC, so the drift
+    /// of a clock is the difference to the local timestamp.
+    pub fn timestamp(&self) -> i64 {
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + of_year;
+        let days = era * 146_097 + of_era - 719_468;
+
+        days * 86_400
+            + i64::from(self.hour) * 3_600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+    }
+}
+
+/// One hop of a traceroute.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hop {
+    pub ttl: u8,
+    /// Addresses that answered the probes, empty when every probe timed out.
+    pub addrs: Vec<String>,
+    /// Round-trip times of the answered probes in milliseconds.
+    pub rtts: Vec<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_rates() {
+        let previous = [InterfaceCounters {
+            interface: "Gi0/1".to_string(),
+            input_bytes: 1_000,
+            input_packets: 10,
+            output_bytes: 5_000,
+            input_errors: 4,
+            ..Default::default()
+        }];
+        let current = [
+            InterfaceCounters {
+                interface: "Gi0/1".to_string(),
+                input_bytes: 11_000,
+                input_packets: 30,
+                output_bytes: 2_000,
+                input_errors: 6,
+                ..Default::default()
+            },
+            InterfaceCounters {
+                interface: "Gi0/2".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let rates = counter_rates(&previous, &current, Duration::from_secs(10));
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].input_bps, 8_000.0);
+        assert_eq!(rates[0].input_pps, 2.0);
+        assert_eq!(rates[0].output_bps, 1_600.0);
+        assert_eq!(rates[0].errors, 0.2);
+    }
+
+    #[test]
+    fn test_date_time() {
+        let time = DateTime {
+            year: 2026,
+            month: 10,
+            day: 14,
+            hour: 10,
+            minute: 15,
+            second: 30,
+        };
+        assert_eq!(time.timestamp(), 1_791_972_930);
+        assert_eq!(DateTime::from_timestamp(time.timestamp()), time);
+        assert_eq!(DateTime::from_timestamp(0).year, 1970);
+        assert_eq!(DateTime::from_timestamp(951_782_400).day, 29);
+    }
+}
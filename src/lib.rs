@@ -1,39 +1,188 @@
-use std::net::ToSocketAddrs;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use strum::EnumString;
 
 pub mod error;
 pub mod generic;
+pub mod parsing;
 pub mod vendor;
 
 #[cfg(test)]
 mod tests;
 
+use generic::connection::{
+    Algorithms, Keepalive, KeyboardInteractive, Pacing, PasswordChange, Pty, Reconnect,
+    SSHConnection,
+};
+use generic::console::{Console, ConsoleConnection};
 use generic::device::NetworkDevice;
+use generic::socket::BindAddress;
+use generic::ssh_config::{JumpHost, SshConfig};
+use vendor::generic::GenericProfile;
 
 #[derive(Debug, Clone, Copy, PartialEq, EnumString)]
 #[strum(serialize_all = "snake_case")]
 pub enum Vendor {
+    AlliedTelesis,
+    Arista,
     Aruba,
+    ArubaCx,
+    ArubaInstant,
+    BrocadeNos,
     Cisco,
+    CiscoAireos,
+    CiscoAsa,
+    CiscoIosxr,
+    CiscoNxos,
+    CiscoSb,
+    DellOs10,
+    Dptech,
+    EdgeSwitch,
+    Extreme,
+    Fiberhome,
+    Fortinet,
+    Generic,
     H3C,
+    H3cSecpath,
+    Hillstone,
+    HpeProcurve,
     Huawei,
+    HuaweiAc,
+    HuaweiUsg,
+    Juniper,
+    Linux,
+    Maipu,
+    Mikrotik,
+    Nokia,
+    PaloAlto,
+    RuckusIcx,
     Ruijie,
+    Tplink,
+    Vyos,
+    Zte,
 }
 
-#[derive(Debug, Clone)]
+impl Vendor {
+    /// Returns the ntc-templates platform name, for `NetworkDevice::execute_parsed`.
+    pub fn ntc_platform(&self) -> Option<&'static str> {
+        Some(match self {
+            Vendor::AlliedTelesis => "alliedtelesis_awplus",
+            Vendor::Arista => "arista_eos",
+            Vendor::Aruba => "aruba_os",
+            Vendor::ArubaCx => "aruba_aoscx",
+            Vendor::Cisco => "cisco_ios",
+            Vendor::CiscoAireos => "cisco_wlc_ssh",
+            Vendor::CiscoAsa => "cisco_asa",
+            Vendor::CiscoIosxr => "cisco_xr",
+            Vendor::CiscoNxos => "cisco_nxos",
+            Vendor::CiscoSb => "cisco_s300",
+            Vendor::DellOs10 => "dell_os10",
+            Vendor::EdgeSwitch => "ubiquiti_edgeswitch",
+            Vendor::Extreme => "extreme_exos",
+            Vendor::Fortinet => "fortinet",
+            Vendor::H3C | Vendor::H3cSecpath => "hp_comware",
+            Vendor::HpeProcurve => "hp_procurve",
+            Vendor::Huawei | Vendor::HuaweiAc | Vendor::HuaweiUsg => "huawei_vrp",
+            Vendor::Juniper => "juniper_junos",
+            Vendor::Linux => "linux",
+            Vendor::Mikrotik => "mikrotik_routeros",
+            Vendor::Nokia => "alcatel_sros",
+            Vendor::PaloAlto => "paloalto_panos",
+            Vendor::RuckusIcx => "ruckus_fastiron",
+            Vendor::Ruijie => "ruijie_os",
+            Vendor::Vyos => "vyos",
+            Vendor::Zte => "zte_zxros",
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ConnectConfig<'a> {
     pub enable_password: Option<&'a str>,
+    /// Private key tried before falling back to password authentication.
+    pub private_key: Option<PrivateKey>,
+    /// Try the identities of a running ssh-agent before falling back to password authentication.
+    pub use_agent: bool,
+    /// Answers keyboard-interactive prompts; every prompt is answered with the password if unset.
+    pub keyboard_interactive: Option<KeyboardInteractive>,
+    /// Key exchange, host key, cipher and MAC preferences applied before the handshake.
+    pub algorithms: Algorithms,
+    /// Sends SSH keepalives in the background while the connection is open.
+    pub keepalive: Option<Keepalive>,
+    /// Re-establishes dropped connections and retries the failed command once.
+    pub reconnect: Option<Reconnect>,
+    /// Timeout for opening the TCP connection, the OS default applies if unset.
+    pub connect_timeout: Option<Duration>,
+    /// Timeout for the authentication exchange, the session timeout applies if unset.
+    pub auth_timeout: Option<Duration>,
+    /// Timeout for blocking SSH operations, 60 seconds if unset.
+    pub session_timeout: Option<Duration>,
+    /// Default timeout for a single command to return to the prompt.
+    pub command_timeout: Option<Duration>,
+    /// Local source address the TCP connection is opened from, for multi-homed hosts.
+    pub bind_address: Option<BindAddress>,
+    /// Jump hosts the connection is tunneled through in order, authenticated with keys only.
+    pub proxy_jump: Vec<JumpHost>,
+    /// Answers the forced password change of first logins, which fails with
+    /// `Error::PasswordChangeRequired` if unset.
+    pub password_change: Option<PasswordChange>,
+    /// Negotiates zlib compression, worth it for large outputs over slow links.
+    pub compression: bool,
+    /// Delays between written commands or characters, for devices dropping input.
+    pub pacing: Option<Pacing>,
+    /// Terminal type and dimensions of the shell, `vt100` with the server default size if unset.
+    pub pty: Option<Pty>,
+    /// Wake-up and telnet settings of console lines opened with `connect_console`.
+    pub console: Option<Console>,
+    /// Prompt, setup and error patterns for `Vendor::Generic`, a default profile if unset.
+    pub generic: Option<GenericProfile>,
 }
 
-impl<'a> Default for ConnectConfig<'a> {
-    fn default() -> Self {
-        Self {
+impl ConnectConfig<'_> {
+    /// Copies the connection level settings, so they can be kept for reconnecting.
+    pub(crate) fn to_static(&self) -> ConnectConfig<'static> {
+        ConnectConfig {
             enable_password: None,
+            private_key: self.private_key.clone(),
+            use_agent: self.use_agent,
+            keyboard_interactive: self.keyboard_interactive.clone(),
+            algorithms: self.algorithms.clone(),
+            keepalive: self.keepalive,
+            reconnect: self.reconnect,
+            connect_timeout: self.connect_timeout,
+            auth_timeout: self.auth_timeout,
+            session_timeout: self.session_timeout,
+            command_timeout: self.command_timeout,
+            bind_address: self.bind_address.clone(),
+            proxy_jump: self.proxy_jump.clone(),
+            password_change: self.password_change.clone(),
+            compression: self.compression,
+            pacing: self.pacing,
+            pty: self.pty.clone(),
+            console: self.console,
+            generic: self.generic.clone(),
         }
     }
 }
 
+/// Private key used for SSH public key authentication.
+#[derive(Debug, Clone)]
+pub enum PrivateKey {
+    /// PEM encoded private key file stored on disk.
+    File {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+    /// PEM encoded private key held in memory.
+    Memory {
+        pem: String,
+        passphrase: Option<String>,
+    },
+}
+
 pub fn connect<A: ToSocketAddrs>(
     vendor: Vendor,
     addr: A,
@@ -43,8 +192,86 @@ pub fn connect<A: ToSocketAddrs>(
     connect_with_config(vendor, addr, username, password, ConnectConfig::default())
 }
 
+/// Connects to a host alias resolved through OpenSSH client configuration(HostName,
+/// Port, User, IdentityFile and ProxyJump), like the `ssh` CLI.
+///
+/// An explicit username or private key takes precedence over the configuration.
+pub fn connect_with_ssh_config(
+    vendor: Vendor,
+    host: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: ConnectConfig,
+    ssh_config: &SshConfig,
+) -> Result<Box<dyn NetworkDevice>, error::Error> {
+    let host = ssh_config.resolve(host);
+    let username = username.or(host.user.as_deref());
+
+    connect_with_config(vendor, host.addr(), username, password, host.apply(config))
+}
+
+/// Identifies the vendor of a device from its login banner and version output.
+pub fn detect_vendor<A: ToSocketAddrs>(
+    addr: A,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: &ConnectConfig,
+) -> Result<Vendor, error::Error> {
+    use generic::connection::Connection;
+
+    let mut connection =
+        SSHConnection::connect(addr, username, password, encoding_rs::UTF_8, config)?;
+
+    generic::detect::detect(&mut connection)
+}
+
+/// Connects with the driver of the detected vendor, for stale inventories.
+///
+/// Detection uses a separate login, the driver connects afresh.
+pub fn connect_auto<A: ToSocketAddrs>(
+    addr: A,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: ConnectConfig,
+) -> Result<(Vendor, Box<dyn NetworkDevice>), error::Error> {
+    let addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .map_err(error::Error::Generic)?
+        .collect();
+
+    let vendor = detect_vendor(&addrs[..], username, password, &config)?;
+    let device = connect_with_config(vendor, &addrs[..], username, password, config)?;
+
+    Ok((vendor, device))
+}
+
+/// Connects by vendor name, either one added through `generic::registry::register_vendor`
+/// or a built-in `Vendor` in snake_case, e.g. `"cisco_nxos"`.
+pub fn connect_named<A: ToSocketAddrs>(
+    name: &str,
+    addr: A,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: ConnectConfig,
+) -> Result<Box<dyn NetworkDevice>, error::Error> {
+    if let Some(factory) = generic::registry::lookup(name) {
+        let addrs: Vec<SocketAddr> = addr
+            .to_socket_addrs()
+            .map_err(error::Error::Generic)?
+            .collect();
+
+        return factory(&addrs, username, password, config);
+    }
+
+    let vendor: Vendor = name
+        .parse()
+        .map_err(|_| generic::registry::unknown_vendor(name))?;
+
+    connect_with_config(vendor, addr, username, password, config)
+}
+
 macro_rules! connect_vendor {
-    ($($vendor:ident => $module:ident::$type:ident),* $(,)?) => {
+    ($($vendor:ident => $($path:ident)::+),* $(,)?) => {
 
         pub fn connect_with_config<A: ToSocketAddrs>(
             vendor: Vendor,
@@ -55,7 +282,25 @@ macro_rules! connect_vendor {
         ) -> Result<Box<dyn NetworkDevice>, error::Error> {
             Ok(match vendor {
                 $(
-                    Vendor::$vendor => vendor::$module::$type::connect(addr, username, password, config)?.into_dyn(),
+                    Vendor::$vendor => vendor::$($path)::+::<SSHConnection>::connect(addr, username, password, config)?.into_dyn(),
+                )*
+            })
+        }
+
+        /// Connects to a device console line through a terminal server port(reverse
+        /// telnet, e.g. `("ts1", 2003)`), waking the line before the usual prompt detection.
+        ///
+        /// The credentials are only used if the device itself asks for a login on the line.
+        pub fn connect_console<A: ToSocketAddrs>(
+            vendor: Vendor,
+            addr: A,
+            username: Option<&str>,
+            password: Option<&str>,
+            config: ConnectConfig,
+        ) -> Result<Box<dyn NetworkDevice>, error::Error> {
+            Ok(match vendor {
+                $(
+                    Vendor::$vendor => vendor::$($path)::+::<ConsoleConnection>::connect(addr, username, password, config)?.into_dyn(),
                 )*
             })
         }
@@ -63,9 +308,41 @@ macro_rules! connect_vendor {
 }
 
 connect_vendor! {
-    Aruba => aruba::ArubaSSH,
-    Cisco => cisco::CiscoSSH,
-    H3C => h3c::H3cSSH,
-    Huawei => huawei::HuaweiSSH,
-    Ruijie => ruijie::RuijieSSH,
+    AlliedTelesis => allied_telesis::AlliedTelesisDevice,
+    Arista => arista::AristaDevice,
+    Aruba => aruba::ArubaDevice,
+    ArubaCx => aruba::cx::ArubaCxDevice,
+    ArubaInstant => aruba::instant::ArubaInstantDevice,
+    BrocadeNos => brocade_nos::BrocadeNosDevice,
+    Cisco => cisco::CiscoDevice,
+    CiscoAireos => cisco::aireos::AireosDevice,
+    CiscoAsa => cisco::asa::AsaDevice,
+    CiscoIosxr => cisco::iosxr::IosxrDevice,
+    CiscoNxos => cisco::nxos::NxosDevice,
+    CiscoSb => cisco::sb::CiscoSbDevice,
+    DellOs10 => dell_os10::DellOs10Device,
+    Dptech => dptech::DptechDevice,
+    EdgeSwitch => edgeswitch::EdgeSwitchDevice,
+    Extreme => extreme::ExtremeDevice,
+    Fiberhome => fiberhome::FiberhomeDevice,
+    Fortinet => fortinet::FortinetDevice,
+    Generic => generic::GenericDevice,
+    H3C => h3c::H3cDevice,
+    H3cSecpath => h3c::secpath::SecpathDevice,
+    Hillstone => hillstone::HillstoneDevice,
+    HpeProcurve => hpe_procurve::ProcurveDevice,
+    Huawei => huawei::HuaweiDevice,
+    HuaweiAc => huawei::ac::HuaweiAcDevice,
+    HuaweiUsg => huawei::usg::UsgDevice,
+    Juniper => juniper::JuniperDevice,
+    Linux => linux::LinuxDevice,
+    Maipu => maipu::MaipuDevice,
+    Mikrotik => mikrotik::MikrotikDevice,
+    Nokia => nokia::NokiaDevice,
+    PaloAlto => paloalto::PaloAltoDevice,
+    RuckusIcx => ruckus_icx::RuckusIcxDevice,
+    Ruijie => ruijie::RuijieDevice,
+    Tplink => tplink::TplinkDevice,
+    Vyos => vyos::VyosDevice,
+    Zte => zte::ZteDevice,
 }
@@ -0,0 +1,257 @@
+//! Template selection by platform and command from an
+//! [ntc-templates](https://github.com/networktocode/ntc-templates) style directory.
+//!
+//! The `index` file of the directory is a comma separated table, `#` lines being
+//! comments. Its first row names the columns, of which `Template` and `Command` are
+//! required and `Platform` is used if present. Commands may abbreviate words with
+//! `sh[[ow]]`, platform and command regexes match from the start, and the first
+//! matching row wins, so longer commands are listed first.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+
+use super::textfsm::TextFsm;
+use crate::error::{Error, TemplateError};
+
+#[derive(Debug)]
+struct Entry {
+    /// Template file names, several being merged record by record.
+    templates: Vec<String>,
+    platform: Option<Regex>,
+    command: Regex,
+}
+
+/// The index of a template directory, compiling templates on first use.
+#[derive(Debug)]
+pub struct TemplateIndex {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    compiled: Mutex<HashMap<String, Arc<TextFsm>>>,
+}
+
+impl TemplateIndex {
+    /// Loads the `index` file of a template directory.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let index = fs::read_to_string(dir.join("index")).map_err(Error::Generic)?;
+
+        Self::new(dir, &index).map_err(|source| Error::Template {
+            name: "index".to_string(),
+            source,
+        })
+    }
+
+    /// Parses an index, whose templates are read from the directory.
+    pub fn new(dir: impl Into<PathBuf>, index: &str) -> Result<Self, TemplateError> {
+        let mut rows = index
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let Some((_, header)) = rows.next() else {
+            return Err(syntax(1, "Missing header"));
+        };
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        let column = |name| columns.iter().position(|&c| c == name);
+
+        let (Some(template), Some(command)) = (column("Template"), column("Command")) else {
+            return Err(syntax(1, "The Template and Command columns are required"));
+        };
+        let platform = column("Platform");
+
+        let mut entries = Vec::new();
+        for (number, row) in rows {
+            let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+            if fields.len() != columns.len() {
+                return Err(syntax(number, "Wrong number of columns"));
+            }
+
+            let regex = |pattern: &str| {
+                Regex::new(&format!("^(?:{})", pattern)).map_err(|e| syntax(number, &e.to_string()))
+            };
+
+            entries.push(Entry {
+                templates: fields[template].split(':').map(str::to_string).collect(),
+                platform: platform.map(|i| regex(fields[i])).transpose()?,
+                command: regex(&completion(fields[command]))?,
+            });
+        }
+
+        Ok(Self {
+            dir: dir.into(),
+            entries,
+            compiled: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the template file names for a command on a platform, if any.
+    pub fn templates(&self, platform: &str, command: &str) -> Option<&[String]> {
+        let command = command.trim();
+
+        self.entries
+            .iter()
+            .find(|e| {
+                e.platform.as_ref().is_none_or(|p| p.is_match(platform))
+                    && e.command.is_match(command)
+            })
+            .map(|e| e.templates.as_slice())
+    }
+
+    /// Parses the output of a command on a platform with the template it maps to.
+    pub fn parse(
+        &self,
+        platform: &str,
+        command: &str,
+        output: &str,
+    ) -> Result<Vec<HashMap<String, String>>, Error> {
+        let Some(templates) = self.templates(platform, command) else {
+            return Err(Error::NoTemplate {
+                platform: platform.to_string(),
+                command: command.to_string(),
+            });
+        };
+
+        let mut records: Vec<HashMap<String, String>> = Vec::new();
+        for name in templates {
+            let parsed = self
+                .compile(name)?
+                .parse(output)
+                .map_err(|source| Error::Template {
+                    name: name.clone(),
+                    source,
+                })?;
+
+            for (i, record) in parsed.into_iter().enumerate() {
+                match records.get_mut(i) {
+                    Some(merged) => merged.extend(record),
+                    None => records.push(record),
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    fn compile(&self, name: &str) -> Result<Arc<TextFsm>, Error> {
+        let mut compiled = self.compiled.lock().expect("Template cache poisoned");
+        if let Some(fsm) = compiled.get(name) {
+            return Ok(fsm.clone());
+        }
+
+        let template = fs::read_to_string(self.dir.join(name)).map_err(Error::Generic)?;
+        let fsm = Arc::new(TextFsm::new(&template).map_err(|source| Error::Template {
+            name: name.to_string(),
+            source,
+        })?);
+
+        compiled.insert(name.to_string(), fsm.clone());
+        Ok(fsm)
+    }
+}
+
+/// Expands the `[[...]]` abbreviations of a command, e.g. `sh[[ow]]` to `sh(?:o(?:w)?)?`.
+fn completion(command: &str) -> String {
+    let mut result = String::new();
+    let mut rest = command;
+
+    while let Some(start) = rest.find("[[") {
+        let Some(end) = rest[start..].find("]]") else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let word = &rest[start + 2..start + end];
+        for c in word.chars() {
+            result.push_str("(?:");
+            result.push_str(&regex::escape(&c.to_string()));
+        }
+        result.push_str(&")?".repeat(word.chars().count()));
+
+        rest = &rest[start + end + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn syntax(line: usize, message: &str) -> TemplateError {
+    TemplateError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = "# Templates of the tests
+Template, Hostname, Platform, Command
+
+cisco_ios_show_ip_interface_brief.textfsm, .*, cisco_ios, sh[[ow]] ip int[[erface]] br[[ief]]
+cisco_ios_show_version.textfsm, .*, cisco_ios, sh[[ow]] ver[[sion]]
+huawei_vrp_display_version.textfsm, .*, huawei_vrp, dis[[play]] ver[[sion]]
+";
+
+    #[test]
+    fn test_completion() {
+        assert_eq!(completion("sh[[ow]] ver"), "sh(?:o(?:w)?)? ver");
+        assert_eq!(completion("show"), "show");
+    }
+
+    #[test]
+    fn test_templates() {
+        let index = TemplateIndex::new("", INDEX).unwrap();
+
+        assert_eq!(
+            index.templates("cisco_ios", "sh ip int br"),
+            Some(&["cisco_ios_show_ip_interface_brief.textfsm".to_string()][..])
+        );
+        assert_eq!(
+            index.templates("cisco_ios", "show version"),
+            Some(&["cisco_ios_show_version.textfsm".to_string()][..])
+        );
+        assert!(index.templates("cisco_ios", "show run").is_none());
+        assert!(index.templates("juniper_junos", "show version").is_none());
+
+        assert!(matches!(
+            TemplateIndex::new("", "Template, Platform\n"),
+            Err(TemplateError::Syntax { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse() {
+        let dir = std::env::temp_dir().join(format!("last_order_index_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("huawei_vrp_display_version.textfsm"),
+            "Value Version (\\S+)\n\nStart\n  ^VRP.*Version ${Version}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("index"), INDEX).unwrap();
+
+        let index = TemplateIndex::load(&dir).unwrap();
+        let output = "Huawei Versatile Routing Platform Software\r\nVRP (R) software, Version 5.170 (S5735 V200R022C00SPC500)\r\n";
+        let records = index
+            .parse("huawei_vrp", "display version", output)
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["Version"], "5.170");
+
+        let error = index
+            .parse("cisco_ios", "show version", output)
+            .unwrap_err();
+        assert!(matches!(error, Error::Generic(_)));
+
+        let error = index.parse("cisco_ios", "show run", output).unwrap_err();
+        assert!(matches!(error, Error::NoTemplate { .. }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
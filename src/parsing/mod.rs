@@ -0,0 +1,5 @@
+//! Parsers turning raw command output into records, for commands without a typed
+//! method on [`crate::generic::device::NetworkDevice`].
+
+pub mod index;
+pub mod textfsm;
@@ -0,0 +1,588 @@
+//! A [TextFSM](https://github.com/google/textfsm) engine, so existing templates turn
+//! `execute()` output into records without leaving Rust.
+//!
+//! A template starts with `Value [Options] Name (regex)` definitions, followed by a
+//! blank line and states of `^regex -> Action` rules. Rule regexes refer to values as
+//! `${Name}` or `$Name`, a literal `$` is written `$$`. The options `Filldown`, `Key`,
+//! `Required`, `List` and `Fillup` are supported, as are the `Next` and `Continue` line
+//! actions, the `Record`, `NoRecord`, `Clear` and `Clearall` record actions, state
+//! transitions including `End` and `EOF`, and `Error`.
+//!
+//! Regexes use the syntax of the `regex` crate, which is that of Python's `re` for
+//! all but look-around and backreferences.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::error::TemplateError;
+
+/// Splits a rule into its regex and action, at the last ` ->`.
+static RULE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.*)\s->(.*)$").expect("Invalid rule regex"));
+
+/// Names of values and states.
+static NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\w+$").expect("Invalid name regex"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineAction {
+    Next,
+    Continue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordAction {
+    NoRecord,
+    Record,
+    Clear,
+    ClearAll,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    filldown: bool,
+    required: bool,
+    list: bool,
+    fillup: bool,
+}
+
+#[derive(Debug)]
+struct Value {
+    name: String,
+    regex: String,
+    options: Options,
+}
+
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    line_action: LineAction,
+    record_action: RecordAction,
+    new_state: Option<String>,
+    /// The message of an `Error` action.
+    error: Option<String>,
+}
+
+/// A compiled TextFSM template.
+#[derive(Debug)]
+pub struct TextFsm {
+    values: Vec<Value>,
+    states: HashMap<String, Vec<Rule>>,
+}
+
+impl TextFsm {
+    /// Compiles a template.
+    pub fn new(template: &str) -> Result<Self, TemplateError> {
+        let mut lines = template.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+        let mut values: Vec<Value> = Vec::new();
+        for (number, line) in lines.by_ref() {
+            let line = line.trim_end();
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            if line.is_empty() {
+                if values.is_empty() {
+                    continue;
+                }
+                break;
+            }
+
+            let Some(definition) = line.strip_prefix("Value ") else {
+                return Err(syntax(number, "Expected a Value definition"));
+            };
+
+            let value = Value::parse(number, definition)?;
+            if values.iter().any(|v| v.name == value.name) {
+                return Err(syntax(number, &format!("Duplicate value {}", value.name)));
+            }
+
+            values.push(value);
+        }
+
+        if values.is_empty() {
+            return Err(syntax(1, "No Value definitions"));
+        }
+
+        let mut states: HashMap<String, Vec<Rule>> = HashMap::new();
+        let mut transitions = Vec::new();
+        let mut current: Option<String> = None;
+        for (number, line) in lines {
+            let text = line.trim();
+            if text.starts_with('#') {
+                continue;
+            }
+
+            if text.is_empty() {
+                current = None;
+                continue;
+            }
+
+            if !line.starts_with(char::is_whitespace) {
+                if !NAME.is_match(text) {
+                    return Err(syntax(number, &format!("Invalid state name {}", text)));
+                }
+                if states.insert(text.to_string(), Vec::new()).is_some() {
+                    return Err(syntax(number, &format!("Duplicate state {}", text)));
+                }
+
+                current = Some(text.to_string());
+                continue;
+            }
+
+            let Some(state) = &current else {
+                return Err(syntax(number, "Rule outside of a state"));
+            };
+
+            let rule = Rule::parse(number, text, &values)?;
+            if let Some(new_state) = &rule.new_state {
+                transitions.push((number, new_state.clone()));
+            }
+
+            states
+                .get_mut(state)
+                .expect("Current state exists")
+                .push(rule);
+        }
+
+        if !states.contains_key("Start") {
+            return Err(syntax(1, "Missing Start state"));
+        }
+
+        for state in ["End", "EOF"] {
+            if states.get(state).is_some_and(|rules| !rules.is_empty()) {
+                return Err(syntax(1, &format!("The {} state must be empty", state)));
+            }
+        }
+
+        for (number, state) in transitions {
+            if state != "End" && state != "EOF" && !states.contains_key(&state) {
+                return Err(syntax(number, &format!("Unknown state {}", state)));
+            }
+        }
+
+        Ok(Self { values, states })
+    }
+
+    /// Returns the value names, in definition order.
+    pub fn header(&self) -> Vec<&str> {
+        self.values.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    /// Parses the output of a command into records keyed by value name.
+    ///
+    /// Every record has all values, empty when not matched. `List` values are joined
+    /// with newlines, which never occur in a matched item.
+    pub fn parse(&self, output: &str) -> Result<Vec<HashMap<String, String>>, TemplateError> {
+        let mut run = Run::new(&self.values);
+        let mut state = "Start";
+
+        'lines: for (index, line) in output.lines().enumerate() {
+            for rule in &self.states[state] {
+                let Some(captures) = rule.regex.captures(line) else {
+                    continue;
+                };
+
+                if let Some(message) = &rule.error {
+                    return Err(TemplateError::Rule {
+                        line: index + 1,
+                        message: message.clone(),
+                    });
+                }
+
+                for name in rule.regex.capture_names().flatten() {
+                    let position = self.values.iter().position(|v| v.name == name);
+                    if let Some(position) = position {
+                        run.assign(position, captures.name(name).map(|m| m.as_str()));
+                    }
+                }
+
+                match rule.record_action {
+                    RecordAction::NoRecord => {}
+                    RecordAction::Record => run.record(),
+                    RecordAction::Clear => run.clear(false),
+                    RecordAction::ClearAll => run.clear(true),
+                }
+
+                if rule.line_action == LineAction::Continue {
+                    continue;
+                }
+
+                match rule.new_state.as_deref() {
+                    Some("End") => return Ok(run.finish()),
+                    Some("EOF") => break 'lines,
+                    Some(new_state) => state = new_state,
+                    None => {}
+                }
+
+                break;
+            }
+        }
+
+        if !self.states.contains_key("EOF") {
+            run.record();
+        }
+
+        Ok(run.finish())
+    }
+}
+
+impl Value {
+    /// Parses the part of a definition after `Value `.
+    fn parse(number: usize, definition: &str) -> Result<Self, TemplateError> {
+        let Some((first, rest)) = definition.trim().split_once(char::is_whitespace) else {
+            return Err(syntax(number, "Expected a value name and regex"));
+        };
+
+        let rest = rest.trim_start();
+        let (options, name, regex) = if rest.starts_with('(') {
+            ("", first, rest)
+        } else {
+            let Some((name, regex)) = rest.split_once(char::is_whitespace) else {
+                return Err(syntax(number, "Expected a value name and regex"));
+            };
+            (first, name, regex.trim_start())
+        };
+
+        if !NAME.is_match(name) {
+            return Err(syntax(number, &format!("Invalid value name {}", name)));
+        }
+
+        if !regex.starts_with('(') || !regex.ends_with(')') {
+            return Err(syntax(number, "The value regex must be in parentheses"));
+        }
+
+        Regex::new(regex).map_err(|e| syntax(number, &e.to_string()))?;
+
+        let mut parsed = Options::default();
+        for option in options.split(',').filter(|o| !o.is_empty()) {
+            match option {
+                "Filldown" => parsed.filldown = true,
+                "Required" => parsed.required = true,
+                "List" => parsed.list = true,
+                "Fillup" => parsed.fillup = true,
+                "Key" => {}
+                _ => return Err(syntax(number, &format!("Unknown option {}", option))),
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            options: parsed,
+        })
+    }
+}
+
+impl Rule {
+    /// Parses a rule, trimmed.
+    fn parse(number: usize, text: &str, values: &[Value]) -> Result<Self, TemplateError> {
+        if !text.starts_with('^') {
+            return Err(syntax(number, "A rule must start with ^"));
+        }
+
+        let (pattern, action) = match RULE.captures(text) {
+            Some(captures) => (captures.get(1), captures.get(2)),
+            None => (None, None),
+        };
+        let pattern = pattern.map_or(text, |m| m.as_str());
+        let action = action.map_or("", |m| m.as_str().trim());
+
+        let regex = Regex::new(&substitute(number, pattern, values)?)
+            .map_err(|e| syntax(number, &e.to_string()))?;
+
+        let mut rule = Self {
+            regex,
+            line_action: LineAction::Next,
+            record_action: RecordAction::NoRecord,
+            new_state: None,
+            error: None,
+        };
+
+        let (first, rest) = action
+            .split_once(char::is_whitespace)
+            .map_or((action, ""), |(first, rest)| (first, rest.trim_start()));
+
+        let target = match actions(first) {
+            Some((line_action, record_action)) => {
+                rule.line_action = line_action;
+                rule.record_action = record_action;
+                rest
+            }
+            None => action,
+        };
+
+        if let Some(message) = target.strip_prefix("Error") {
+            rule.error = Some(match message.trim().trim_matches('"') {
+                "" => "Error action in state".to_string(),
+                message => message.to_string(),
+            });
+        } else if !target.is_empty() {
+            if !NAME.is_match(target) {
+                return Err(syntax(number, &format!("Invalid action {}", action)));
+            }
+            rule.new_state = Some(target.to_string());
+        }
+
+        if rule.line_action == LineAction::Continue && rule.new_state.is_some() {
+            return Err(syntax(number, "Continue cannot change the state"));
+        }
+
+        Ok(rule)
+    }
+}
+
+/// Parses `LineAction`, `RecordAction` or `LineAction.RecordAction`.
+fn actions(text: &str) -> Option<(LineAction, RecordAction)> {
+    let line_action = |text| match text {
+        "Next" => Some(LineAction::Next),
+        "Continue" => Some(LineAction::Continue),
+        _ => None,
+    };
+    let record_action = |text| match text {
+        "NoRecord" => Some(RecordAction::NoRecord),
+        "Record" => Some(RecordAction::Record),
+        "Clear" => Some(RecordAction::Clear),
+        "Clearall" => Some(RecordAction::ClearAll),
+        _ => None,
+    };
+
+    match text.split_once('.') {
+        Some((line, record)) => Some((line_action(line)?, record_action(record)?)),
+        None => line_action(text)
+            .map(|line| (line, RecordAction::NoRecord))
+            .or_else(|| record_action(text).map(|record| (LineAction::Next, record))),
+    }
+}
+
+/// Replaces the `$Name` and `${Name}` references of a rule by named groups.
+fn substitute(number: usize, pattern: &str, values: &[Value]) -> Result<String, TemplateError> {
+    let mut result = String::new();
+    let mut rest = pattern;
+
+    while let Some(position) = rest.find('$') {
+        result.push_str(&rest[..position]);
+        let after = &rest[position + 1..];
+
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (name, after) = match after.strip_prefix('{') {
+            Some(inner) => {
+                let Some(end) = inner.find('}') else {
+                    return Err(syntax(number, "Unclosed ${"));
+                };
+                (&inner[..end], &inner[end + 1..])
+            }
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                after.split_at(end)
+            }
+        };
+
+        let Some(value) = values.iter().find(|v| v.name == name) else {
+            return Err(syntax(number, &format!("Unknown value ${}", name)));
+        };
+
+        result.push_str(&format!("(?P<{}>{}", value.name, &value.regex[1..]));
+        rest = after;
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn syntax(line: usize, message: &str) -> TemplateError {
+    TemplateError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// The values being collected and the records saved while parsing.
+struct Run<'a> {
+    values: &'a [Value],
+    current: Vec<Vec<String>>,
+    records: Vec<Vec<Vec<String>>>,
+}
+
+impl<'a> Run<'a> {
+    fn new(values: &'a [Value]) -> Self {
+        Self {
+            values,
+            current: vec![Vec::new(); values.len()],
+            records: Vec::new(),
+        }
+    }
+
+    fn assign(&mut self, index: usize, text: Option<&str>) {
+        let options = &self.values[index].options;
+
+        let Some(text) = text else {
+            if !options.list {
+                self.current[index].clear();
+            }
+            return;
+        };
+
+        if options.list {
+            self.current[index].push(text.to_string());
+        } else {
+            self.current[index] = vec![text.to_string()];
+        }
+
+        if options.fillup && !text.is_empty() {
+            for record in self.records.iter_mut().rev() {
+                if !empty(&record[index]) {
+                    break;
+                }
+                record[index] = vec![text.to_string()];
+            }
+        }
+    }
+
+    /// Saves the current record unless a `Required` value or every value is empty.
+    fn record(&mut self) {
+        let missing = self
+            .values
+            .iter()
+            .zip(&self.current)
+            .any(|(value, current)| value.options.required && empty(current));
+
+        if !missing && !self.current.iter().all(|current| empty(current)) {
+            self.records.push(self.current.clone());
+        }
+
+        self.clear(false);
+    }
+
+    /// Clears the current values, except `Filldown` ones unless `all`.
+    fn clear(&mut self, all: bool) {
+        for (value, current) in self.values.iter().zip(&mut self.current) {
+            if all || !value.options.filldown {
+                current.clear();
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<HashMap<String, String>> {
+        self.records
+            .into_iter()
+            .map(|record| {
+                self.values
+                    .iter()
+                    .zip(record)
+                    .map(|(value, items)| (value.name.clone(), items.join("\n")))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn empty(items: &[String]) -> bool {
+    items.iter().all(|item| item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACES: &str = r#"Value Required Interface (\S+)
+Value Status (up|down)
+Value List Address (\d+\.\d+\.\d+\.\d+)
+Value Filldown Vrf (\S+)
+
+Start
+  # Interfaces of a VRF
+  ^VRF ${Vrf}$$
+  ^\S+ is -> Continue.Record
+  ^${Interface} is ${Status}
+  ^\s+address $Address
+  ^% -> Error "Invalid input"
+"#;
+
+    const OUTPUT: &str = "VRF mgmt
+GigabitEthernet0/0 is up
+  address 192.168.1.1
+  address 192.168.1.2
+GigabitEthernet0/1 is down
+";
+
+    fn field<'a>(record: &'a HashMap<String, String>, name: &str) -> &'a str {
+        record[name].as_str()
+    }
+
+    #[test]
+    fn test_parse() {
+        let fsm = TextFsm::new(INTERFACES).unwrap();
+        assert_eq!(fsm.header(), vec!["Interface", "Status", "Address", "Vrf"]);
+
+        let records = fsm.parse(OUTPUT).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(field(&records[0], "Interface"), "GigabitEthernet0/0");
+        assert_eq!(field(&records[0], "Status"), "up");
+        assert_eq!(field(&records[0], "Address"), "192.168.1.1\n192.168.1.2");
+        assert_eq!(field(&records[0], "Vrf"), "mgmt");
+
+        assert_eq!(field(&records[1], "Interface"), "GigabitEthernet0/1");
+        assert_eq!(field(&records[1], "Status"), "down");
+        assert_eq!(field(&records[1], "Address"), "");
+        assert_eq!(field(&records[1], "Vrf"), "mgmt");
+
+        let error = fsm.parse("% Invalid input detected\n").unwrap_err();
+        assert!(matches!(error, TemplateError::Rule { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_states() {
+        let template = r"Value Name (\S+)
+Value Fillup Total (\d+)
+
+Start
+  ^Users -> Users
+
+Users
+  ^Total ${Total}
+  ^end -> End
+  ^${Name}$$ -> Record
+
+EOF
+";
+        let fsm = TextFsm::new(template).unwrap();
+
+        let records = fsm
+            .parse("alice\nUsers\nbob\ncarol\nTotal 2\nend\ndave\n")
+            .unwrap();
+        let names: Vec<_> = records.iter().map(|r| field(r, "Name")).collect();
+        assert_eq!(names, vec!["bob", "carol"]);
+        assert!(records.iter().all(|r| field(r, "Total") == "2"));
+
+        // The explicit EOF state drops the last record.
+        assert!(fsm.parse("Users\nTotal 1\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let line = |template| match TextFsm::new(template) {
+            Err(TemplateError::Syntax { line, .. }) => line,
+            other => panic!("Expected a syntax error, got {:?}", other),
+        };
+
+        assert_eq!(line("Value Name \\S+\n\nStart\n"), 1);
+        assert_eq!(line("Value Name (\\S+)\n\nStart\n  ^$Unknown\n"), 4);
+        assert_eq!(
+            line("Value Name (\\S+)\n\nStart\n  ^$Name -> Continue Other\n"),
+            4
+        );
+        assert_eq!(line("Value Name (\\S+)\n\nStart\n  ^$Name -> Missing\n"), 4);
+        assert_eq!(line("Value Name (\\S+)\n\nOther\n  ^$Name\n"), 1);
+    }
+}